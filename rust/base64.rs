@@ -0,0 +1,175 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Base64 encoding of serialized messages, for embedding them in text
+//! contexts like HTTP headers, URLs or environment variables.
+
+use crate::ParseError;
+
+/// The base64 alphabet to use, as defined by RFC 4648.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alphabet {
+    /// The standard alphabet (`+` and `/`), with `=` padding.
+    Standard,
+    /// The URL and filename safe alphabet (`-` and `_`), without padding.
+    UrlSafe,
+}
+
+impl Alphabet {
+    fn symbols(self) -> &'static [u8; 64] {
+        match self {
+            Alphabet::Standard => {
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+            }
+            Alphabet::UrlSafe => {
+                b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_"
+            }
+        }
+    }
+
+    fn value(self, symbol: u8) -> Option<u8> {
+        self.symbols().iter().position(|&s| s == symbol).map(|v| v as u8)
+    }
+}
+
+/// Encodes `data` as base64.
+pub fn encode(data: &[u8], alphabet: Alphabet) -> String {
+    let symbols = alphabet.symbols();
+    let mut out = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits =
+            chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | u32::from(b) << (16 - 8 * i));
+        // A chunk of n bytes is encoded by n + 1 symbols.
+        for i in 0..=chunk.len() {
+            out.push(symbols[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+        if alphabet == Alphabet::Standard {
+            for _ in chunk.len()..3 {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64 `text`. Padding is optional for either alphabet.
+///
+/// Decoding is strict about the unused low bits of the last symbol, which
+/// must be zero, so each message has a single valid encoding (e.g. `Zh==` is
+/// rejected rather than decoded like `Zg==`).
+///
+/// On failure, the error's offset points at the offending character.
+pub fn decode(text: &str, alphabet: Alphabet) -> Result<Vec<u8>, ParseError> {
+    let text = text.as_bytes();
+    let unpadded = text.iter().rposition(|&c| c != b'=').map_or(0, |i| i + 1);
+    if text.len() - unpadded > 2 || (unpadded < text.len() && text.len() % 4 != 0) {
+        return Err(ParseError::at(unpadded));
+    }
+    // A trailing group of a single symbol can't encode a whole byte.
+    if unpadded % 4 == 1 {
+        return Err(ParseError::at(unpadded - 1));
+    }
+
+    let mut out = Vec::with_capacity(unpadded / 4 * 3 + 2);
+    for (group, chunk) in text[..unpadded].chunks(4).enumerate() {
+        let mut bits = 0u32;
+        for (i, &symbol) in chunk.iter().enumerate() {
+            let value = alphabet.value(symbol).ok_or_else(|| ParseError::at(group * 4 + i))?;
+            bits |= u32::from(value) << (18 - 6 * i);
+        }
+        // Reject non-canonical encodings, whose last symbol has bits set past
+        // the final byte.
+        if bits & (0x00ff_ffff >> (8 * (chunk.len() - 1))) != 0 {
+            return Err(ParseError::at(group * 4 + chunk.len() - 1));
+        }
+        // n symbols carry n - 1 whole bytes.
+        out.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vectors from RFC 4648, section 10.
+    const VECTORS: [(&str, &str); 7] = [
+        ("", ""),
+        ("f", "Zg=="),
+        ("fo", "Zm8="),
+        ("foo", "Zm9v"),
+        ("foob", "Zm9vYg=="),
+        ("fooba", "Zm9vYmE="),
+        ("foobar", "Zm9vYmFy"),
+    ];
+
+    #[test]
+    fn test_standard() {
+        for (data, text) in VECTORS {
+            assert_eq!(encode(data.as_bytes(), Alphabet::Standard), text);
+            assert_eq!(decode(text, Alphabet::Standard).unwrap(), data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_url_safe() {
+        for (data, text) in VECTORS {
+            let unpadded = text.trim_end_matches('=');
+            assert_eq!(encode(data.as_bytes(), Alphabet::UrlSafe), unpadded);
+            assert_eq!(decode(unpadded, Alphabet::UrlSafe).unwrap(), data.as_bytes());
+            assert_eq!(decode(text, Alphabet::UrlSafe).unwrap(), data.as_bytes());
+        }
+        assert_eq!(encode(&[0xfb, 0xff], Alphabet::UrlSafe), "-_8");
+        assert_eq!(encode(&[0xfb, 0xff], Alphabet::Standard), "+/8=");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("Zm9v!", Alphabet::Standard).unwrap_err().offset(), Some(4));
+        assert_eq!(decode("-_8", Alphabet::Standard).unwrap_err().offset(), Some(0));
+        assert_eq!(decode("Zm9vY", Alphabet::Standard).unwrap_err().offset(), Some(4));
+        assert_eq!(decode("Zg===", Alphabet::Standard).unwrap_err().offset(), Some(2));
+        assert_eq!(decode("Zg=", Alphabet::Standard).unwrap_err().offset(), Some(2));
+    }
+
+    #[test]
+    fn test_decode_rejects_non_canonical_trailing_bits() {
+        assert_eq!(decode("Zh==", Alphabet::Standard).unwrap_err().offset(), Some(1));
+        assert_eq!(decode("Zm9=", Alphabet::Standard).unwrap_err().offset(), Some(2));
+        assert_eq!(decode("Zm9vYh", Alphabet::UrlSafe).unwrap_err().offset(), Some(5));
+        // Every byte value round-trips through its canonical encoding.
+        for b in 0..=255u8 {
+            for data in [vec![b], vec![0, b]] {
+                let text = encode(&data, Alphabet::Standard);
+                assert_eq!(decode(&text, Alphabet::Standard).unwrap(), data);
+            }
+        }
+    }
+}
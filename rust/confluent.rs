@@ -0,0 +1,179 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The Confluent Schema Registry wire format, used to frame Protobuf messages
+//! in Kafka records.
+//!
+//! A framed record is a zero magic byte, the big-endian 4-byte ID of the
+//! writer's schema, the indexes locating the message type within that schema
+//! and finally the serialized message.
+
+use crate::wire::{read_varint, write_varint};
+use crate::ParseError;
+
+const MAGIC_BYTE: u8 = 0;
+
+/// A record in the Confluent wire format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    /// The ID of the schema in the schema registry.
+    pub schema_id: u32,
+    /// The path to the message type within the schema: the index of the
+    /// top-level message, followed by the indexes of nested messages. `[0]`
+    /// is the first message in the file.
+    pub message_indexes: Vec<i32>,
+    /// The serialized message.
+    pub payload: &'a [u8],
+}
+
+/// Resolves the schema registry coordinates of message types, so records can
+/// be framed by message name only.
+pub trait SchemaIdResolver {
+    /// Returns the schema ID and message indexes (see [`Envelope`]) for the
+    /// message type with the fully qualified name `message`, or `None` if it
+    /// isn't registered.
+    fn resolve(&self, message: &str) -> Option<(u32, Vec<i32>)>;
+}
+
+/// Appends `payload`, a serialized message, framed for schema `schema_id` to
+/// `out`.
+pub fn write_envelope(out: &mut Vec<u8>, schema_id: u32, message_indexes: &[i32], payload: &[u8]) {
+    out.push(MAGIC_BYTE);
+    out.extend_from_slice(&schema_id.to_be_bytes());
+    if message_indexes == [0] {
+        // The common case of the first message gets a shorthand encoding.
+        out.push(0);
+    } else {
+        write_zigzag(out, message_indexes.len() as i64);
+        for &index in message_indexes {
+            write_zigzag(out, i64::from(index));
+        }
+    }
+    out.extend_from_slice(payload);
+}
+
+/// Frames `payload`, a serialized `message`, with the coordinates returned by
+/// `resolver`. Returns `None` if the message type isn't registered.
+pub fn frame(resolver: &impl SchemaIdResolver, message: &str, payload: &[u8]) -> Option<Vec<u8>> {
+    let (schema_id, message_indexes) = resolver.resolve(message)?;
+    let mut out = Vec::new();
+    write_envelope(&mut out, schema_id, &message_indexes, payload);
+    Some(out)
+}
+
+/// Parses the framing of a Confluent wire format record. The payload is not
+/// parsed.
+pub fn parse_envelope(data: &[u8]) -> Result<Envelope<'_>, ParseError> {
+    if data.first() != Some(&MAGIC_BYTE) {
+        return Err(ParseError::at(0));
+    }
+    let schema_id = data.get(1..5).ok_or_else(|| ParseError::at(data.len()))?;
+    let schema_id = u32::from_be_bytes(schema_id.try_into().unwrap());
+
+    let (count, mut pos) = read_zigzag(data, 5)?;
+    let message_indexes = if count == 0 {
+        vec![0]
+    } else {
+        // Every index takes at least a byte, which bounds the allocation.
+        if count < 0 || count as u64 > (data.len() - pos) as u64 {
+            return Err(ParseError::at(5));
+        }
+        let mut indexes = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let start = pos;
+            let (index, next) = read_zigzag(data, pos)?;
+            indexes.push(i32::try_from(index).map_err(|_| ParseError::at(start))?);
+            pos = next;
+        }
+        indexes
+    };
+    Ok(Envelope { schema_id, message_indexes, payload: &data[pos..] })
+}
+
+fn write_zigzag(out: &mut Vec<u8>, value: i64) {
+    write_varint(out, ((value << 1) ^ (value >> 63)) as u64).expect("writing to a Vec can't fail");
+}
+
+fn read_zigzag(data: &[u8], pos: usize) -> Result<(i64, usize), ParseError> {
+    let (value, pos) = read_varint(data, pos)?;
+    Ok(((value >> 1) as i64 ^ -((value & 1) as i64), pos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_message_shorthand() {
+        let mut out = Vec::new();
+        write_envelope(&mut out, 0x01020304, &[0], b"\x08\x01");
+        assert_eq!(out, b"\x00\x01\x02\x03\x04\x00\x08\x01");
+
+        let envelope = parse_envelope(&out).unwrap();
+        assert_eq!(
+            envelope,
+            Envelope { schema_id: 0x01020304, message_indexes: vec![0], payload: b"\x08\x01" }
+        );
+    }
+
+    #[test]
+    fn test_nested_message() {
+        let mut out = Vec::new();
+        write_envelope(&mut out, 7, &[1, 0, 2], b"");
+        // Count and indexes are zigzag encoded.
+        assert_eq!(out, b"\x00\x00\x00\x00\x07\x06\x02\x00\x04");
+        assert_eq!(parse_envelope(&out).unwrap().message_indexes, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_frame_with_resolver() {
+        struct Registry;
+        impl SchemaIdResolver for Registry {
+            fn resolve(&self, message: &str) -> Option<(u32, Vec<i32>)> {
+                (message == "pkg.Msg").then(|| (42, vec![0]))
+            }
+        }
+
+        let framed = frame(&Registry, "pkg.Msg", b"\x08\x01").unwrap();
+        assert_eq!(parse_envelope(&framed).unwrap().schema_id, 42);
+        assert_eq!(frame(&Registry, "pkg.Other", b""), None);
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert_eq!(parse_envelope(b"").unwrap_err().offset(), Some(0));
+        assert_eq!(parse_envelope(b"\x01\x00\x00\x00\x01\x00").unwrap_err().offset(), Some(0));
+        assert_eq!(parse_envelope(b"\x00\x00\x00").unwrap_err().offset(), Some(3));
+        // Claims 100 indexes but has none.
+        assert_eq!(parse_envelope(b"\x00\x00\x00\x00\x01\xc8\x01").unwrap_err().offset(), Some(5));
+        // Negative count.
+        assert!(parse_envelope(b"\x00\x00\x00\x00\x01\x01").is_err());
+    }
+}
@@ -30,13 +30,17 @@
 
 // Rust Protobuf runtime using the C++ kernel.
 
+use crate::PtrAndLen;
 use std::alloc;
 use std::alloc::Layout;
+use std::any::Any;
 use std::cell::UnsafeCell;
+use std::ffi::c_void;
 use std::fmt;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
 use std::ops::Deref;
+use std::panic::{self, AssertUnwindSafe};
 use std::ptr::NonNull;
 use std::slice;
 
@@ -98,6 +102,10 @@ impl Drop for Arena {
     }
 }
 
+/// The size of the largest message the C++ kernel can serialize or parse. It
+/// stores sizes as `int`.
+pub const MAX_MESSAGE_SIZE: usize = i32::MAX as usize;
+
 /// Represents serialized Protobuf wire format data. It's typically produced by
 /// `<Message>.serialize()`.
 ///
@@ -143,6 +151,68 @@ impl fmt::Debug for SerializedData {
     }
 }
 
+/// An FFI-safe handle to a Rust `FnMut(&[u8])`, through which C++ hands
+/// serialized data back to Rust piece by piece. Created by
+/// [`with_chunk_sink`].
+///
+/// This struct is ABI compatible with the equivalent struct on the C++ side.
+#[repr(C)]
+pub struct ChunkSink<'a> {
+    callback: unsafe extern "C" fn(sink: *mut c_void, chunk: PtrAndLen) -> bool,
+    sink: *mut c_void,
+    _phantom: PhantomData<&'a mut ()>,
+}
+
+/// Calls `serialize` with a `ChunkSink` forwarding to `sink`, and returns the
+/// result of `serialize`.
+///
+/// A panic in `sink` must not unwind through the C++ frames that call it. It
+/// is caught instead, the sink reports failure to C++ so that serialization
+/// stops, and the panic is resumed once `serialize` returns.
+pub fn with_chunk_sink<F: FnMut(&[u8])>(
+    sink: &mut F,
+    serialize: impl FnOnce(ChunkSink<'_>) -> bool,
+) -> bool {
+    struct State<'a, F> {
+        sink: &'a mut F,
+        panic: Option<Box<dyn Any + Send>>,
+    }
+
+    unsafe extern "C" fn call<F: FnMut(&[u8])>(state: *mut c_void, chunk: PtrAndLen) -> bool {
+        let state = &mut *state.cast::<State<F>>();
+        if state.panic.is_some() {
+            return false;
+        }
+        let sink = &mut state.sink;
+        match panic::catch_unwind(AssertUnwindSafe(|| sink(chunk.as_ref()))) {
+            Ok(()) => true,
+            Err(payload) => {
+                state.panic = Some(payload);
+                false
+            }
+        }
+    }
+
+    let mut state = State { sink, panic: None };
+    let ok = serialize(ChunkSink {
+        callback: call::<F>,
+        sink: (&mut state as *mut State<F>).cast(),
+        _phantom: PhantomData,
+    });
+    if let Some(payload) = state.panic {
+        panic::resume_unwind(payload);
+    }
+    ok
+}
+
+// The thunk ABI types must match the layouts asserted in cpp_api.h.
+const _: () = {
+    use std::mem::size_of;
+    assert!(size_of::<SerializedData>() == 2 * size_of::<usize>());
+    assert!(size_of::<PtrAndLen>() == 2 * size_of::<usize>());
+    assert!(size_of::<ChunkSink>() == 2 * size_of::<usize>());
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -162,4 +232,31 @@ mod tests {
         let serialized_data = SerializedData { data: NonNull::new(ptr).unwrap(), len: len };
         assert_eq!(&*serialized_data, b"Hello world");
     }
+
+    // Stands in for the C++ thunk, feeding `chunk` to the sink.
+    fn feed(sink: ChunkSink, chunk: &[u8]) -> bool {
+        unsafe { (sink.callback)(sink.sink, PtrAndLen { ptr: chunk.as_ptr(), len: chunk.len() }) }
+    }
+
+    #[test]
+    fn test_chunk_sink_forwards_to_closure() {
+        let mut chunks = Vec::new();
+        let mut push = |chunk: &[u8]| chunks.push(chunk.to_vec());
+        assert!(with_chunk_sink(&mut push, |sink| feed(sink, b"chunk")));
+        assert_eq!(chunks, [b"chunk"]);
+    }
+
+    #[test]
+    fn test_chunk_sink_resumes_panics() {
+        let mut sink_result = None;
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut fail = |_: &[u8]| panic!("sink failed");
+            with_chunk_sink(&mut fail, |sink| {
+                sink_result = Some(feed(sink, b"chunk"));
+                true
+            })
+        }));
+        assert_eq!(sink_result, Some(false));
+        assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "sink failed");
+    }
 }
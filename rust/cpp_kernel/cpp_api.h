@@ -33,8 +33,15 @@
 #ifndef GOOGLE_PROTOBUF_RUST_CPP_KERNEL_CPP_H__
 #define GOOGLE_PROTOBUF_RUST_CPP_KERNEL_CPP_H__
 
+#include <algorithm>
 #include <cstddef>
+#include <limits>
+#include <type_traits>
 
+#include "absl/log/absl_check.h"
+#include "absl/log/absl_log.h"
+#include "google/protobuf/io/coded_stream.h"
+#include "google/protobuf/io/zero_copy_stream_impl_lite.h"
 #include "google/protobuf/message.h"
 
 namespace google {
@@ -62,13 +69,93 @@ extern "C" struct SerializedData {
 // This function is defined in `rust_alloc_for_cpp_api.rs`.
 extern "C" void* __pb_rust_alloc(size_t size, size_t align);
 
-inline SerializedData SerializeMsg(const google::protobuf::Message* msg) {
-  size_t len = msg->ByteSizeLong();
-  void* bytes = __pb_rust_alloc(len, alignof(char));
-  if (!msg->SerializeToArray(bytes, static_cast<int>(len))) {
+// The C++ kernel stores sizes as `int`, so it can't serialize or parse
+// messages of 2GiB or more.
+constexpr size_t kMaxMessageSize =
+    static_cast<size_t>(std::numeric_limits<int>::max());
+
+// Returns the serialized size of `msg`. Callers must check it against
+// `kMaxMessageSize` before serializing.
+inline size_t SerializedSize(const google::protobuf::Message* msg) {
+  return msg->ByteSizeLong();
+}
+
+// Serializes `msg` into `data`, which must have room for `len` bytes as
+// returned by `SerializedSize(msg)`. `len` must not exceed `kMaxMessageSize`.
+inline void SerializeMsgToArray(const google::protobuf::Message* msg, char* data,
+                                size_t len) {
+  ABSL_DCHECK_LE(len, kMaxMessageSize);
+  if (!msg->SerializeToArray(data, static_cast<int>(len))) {
+    ABSL_LOG(FATAL) << "Couldn't serialize the message.";
+  }
+}
+
+// Serializes `msg` into `out`. Returns false, leaving `out` untouched, if the
+// message is larger than `kMaxMessageSize`.
+inline bool SerializeMsg(const google::protobuf::Message* msg,
+                         SerializedData* out) {
+  size_t len = SerializedSize(msg);
+  if (len > kMaxMessageSize) {
+    return false;
+  }
+  char* bytes = static_cast<char*>(__pb_rust_alloc(len, alignof(char)));
+  SerializeMsgToArray(msg, bytes, len);
+  *out = SerializedData(bytes, len);
+  return true;
+}
+
+// Like `SerializeMsg()`, but doesn't require required fields to be set.
+inline bool SerializeMsgPartial(const google::protobuf::Message* msg,
+                                SerializedData* out) {
+  size_t len = SerializedSize(msg);
+  if (len > kMaxMessageSize) {
+    return false;
+  }
+  char* bytes = static_cast<char*>(__pb_rust_alloc(len, alignof(char)));
+  if (!msg->SerializePartialToArray(bytes, static_cast<int>(len))) {
+    ABSL_LOG(FATAL) << "Couldn't serialize the message.";
+  }
+  *out = SerializedData(bytes, len);
+  return true;
+}
+
+// Like `SerializeMsg()`, but uses deterministic serialization (e.g. map
+// entries are ordered by key).
+inline bool SerializeMsgDeterministic(const google::protobuf::Message* msg,
+                                      SerializedData* out) {
+  size_t len = SerializedSize(msg);
+  if (len > kMaxMessageSize) {
+    return false;
+  }
+  char* bytes = static_cast<char*>(__pb_rust_alloc(len, alignof(char)));
+  google::protobuf::io::ArrayOutputStream array(bytes, static_cast<int>(len));
+  google::protobuf::io::CodedOutputStream stream(&array);
+  stream.SetSerializationDeterministic(true);
+  if (!msg->SerializeToCodedStream(&stream)) {
     ABSL_LOG(FATAL) << "Couldn't serialize the message.";
   }
-  return SerializedData(static_cast<char*>(bytes), len);
+  *out = SerializedData(bytes, len);
+  return true;
+}
+
+// Parses `data` into `msg`.
+//
+// Inputs larger than `kMaxMessageSize` are rejected rather than having their
+// length silently truncated to `int`.
+inline bool DeserializeMsg(google::protobuf::Message* msg, SerializedData data) {
+  if (data.len > kMaxMessageSize) {
+    return false;
+  }
+  return msg->ParseFromArray(data.data, static_cast<int>(data.len));
+}
+
+// Like `DeserializeMsg()`, but doesn't require required fields to be set.
+inline bool DeserializeMsgPartial(google::protobuf::Message* msg,
+                                  SerializedData data) {
+  if (data.len > kMaxMessageSize) {
+    return false;
+  }
+  return msg->ParsePartialFromArray(data.data, static_cast<int>(data.len));
 }
 
 // Represents an ABI-stable version of &[u8]/string_view (borrowed slice of
@@ -81,6 +168,63 @@ struct PtrAndLen {
   PtrAndLen(const char* ptr, size_t len) : ptr(ptr), len(len) {}
 };
 
+// A Rust `FnMut(&[u8])` that receives serialized data piece by piece.
+//
+// Rust and C++ versions of this struct are ABI compatible. `sink` is only
+// valid for the duration of the thunk call that received it. `callback`
+// returns false if the Rust closure panicked, after which it must not be
+// called again.
+struct ChunkSink {
+  bool (*callback)(void* sink, PtrAndLen chunk);
+  void* sink;
+};
+
+// The Rust side of the thunk ABI (rust/cpp.rs, rust/shared.rs) mirrors these
+// layouts with `#[repr(C)]` types and asserts the same sizes.
+static_assert(std::is_standard_layout<SerializedData>::value, "");
+static_assert(sizeof(SerializedData) == 2 * sizeof(void*), "");
+static_assert(offsetof(SerializedData, len) == sizeof(void*), "");
+static_assert(std::is_standard_layout<PtrAndLen>::value, "");
+static_assert(sizeof(PtrAndLen) == 2 * sizeof(void*), "");
+static_assert(offsetof(PtrAndLen, len) == sizeof(void*), "");
+static_assert(std::is_standard_layout<ChunkSink>::value, "");
+static_assert(sizeof(ChunkSink) == 2 * sizeof(void*), "");
+static_assert(offsetof(ChunkSink, sink) == sizeof(void*), "");
+
+// Serializes `msg` into `sink` in chunks of at most `chunk_size` bytes, without
+// materializing the whole serialized message at once. Returns false, without
+// calling `sink`, if the message is larger than `kMaxMessageSize`, and also
+// if `sink` fails, in which case serialization stops early.
+inline bool SerializeMsgChunked(const google::protobuf::Message* msg,
+                                size_t chunk_size, ChunkSink sink) {
+  class SinkStream final : public google::protobuf::io::CopyingOutputStream {
+   public:
+    explicit SinkStream(ChunkSink sink) : sink_(sink) {}
+    bool Write(const void* buffer, int size) override {
+      return sink_.callback(sink_.sink,
+                            PtrAndLen(static_cast<const char*>(buffer), size));
+    }
+
+   private:
+    ChunkSink sink_;
+  };
+
+  ABSL_CHECK_GT(chunk_size, 0u);
+  size_t len = SerializedSize(msg);
+  if (len > kMaxMessageSize) {
+    return false;
+  }
+  // Don't allocate more buffer than the message needs, whatever the chunk
+  // size.
+  size_t buffer_size =
+      std::max<size_t>(1, std::min({chunk_size, len, kMaxMessageSize}));
+  SinkStream stream(sink);
+  google::protobuf::io::CopyingOutputStreamAdaptor adaptor(
+      &stream, static_cast<int>(buffer_size));
+  // Only a failing sink can make this fail.
+  return msg->SerializeToZeroCopyStream(&adaptor) && adaptor.Flush();
+}
+
 }  // namespace rust_internal
 }  // namespace protobuf
 }  // namespace google
@@ -13,7 +13,7 @@ load(
 
 visibility(["//rust/..."])
 
-def rust_proto_library(name, deps, visibility = [], **args):
+def rust_proto_library(name, deps, visibility = [], kernel = None, **args):
     """Declares all the boilerplate needed to use Rust protobufs conveniently. 
 
     Hopefully no user will ever need to read this code.
@@ -22,16 +22,25 @@ def rust_proto_library(name, deps, visibility = [], **args):
     Args:
         name: name of the Rust protobuf target.
         deps: proto_library target for which to generate Rust gencode.
+        kernel: "upb" or "cpp" to always use that kernel for this target,
+          ignoring `//rust:rust_proto_library_kernel`. All Rust protos linked
+          into one binary must still use the same kernel.
         **args: other args passed to the rust_<kernel>_proto_library targets.
     """
     if not name.endswith("_rust_proto"):
         fail("Name of each rust_proto_library target should end with `_rust_proto`")
-    native.alias(
-        name = name,
+    if kernel == None:
         actual = select({
             "//rust:use_upb_kernel": name + "_upb_kernel",
             "//conditions:default": name + "_cpp_kernel",
-        }),
+        })
+    elif kernel in ["upb", "cpp"]:
+        actual = name + "_" + kernel + "_kernel"
+    else:
+        fail("kernel must be \"upb\" or \"cpp\", got \"{}\"".format(kernel))
+    native.alias(
+        name = name,
+        actual = actual,
         visibility = visibility,
     )
 
@@ -0,0 +1,112 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Human-readable rendering of field values for logs and debug output.
+
+use crate::base64;
+use std::fmt;
+
+/// How to render binary data, such as the value of a `bytes` field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BytesStyle {
+    /// Lowercase hex digits, e.g. `00ff`.
+    Hex,
+    /// A C-escaped string in double quotes, e.g. `"a\n\377"`, the same as
+    /// text format. Printable ASCII is kept as is.
+    CEscaped,
+    /// Standard base64.
+    Base64,
+    /// Only the length, e.g. `<2 bytes>`, for data that should not be logged.
+    LengthOnly,
+}
+
+/// Displays `data` in the given `style`.
+///
+/// The output never contains control characters, so it is safe to write to
+/// a terminal.
+pub fn bytes(data: &[u8], style: BytesStyle) -> BytesDisplay<'_> {
+    BytesDisplay { data, style }
+}
+
+/// Renders binary data, see [`bytes()`].
+#[derive(Debug, Clone, Copy)]
+pub struct BytesDisplay<'a> {
+    data: &'a [u8],
+    style: BytesStyle,
+}
+
+impl fmt::Display for BytesDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.style {
+            BytesStyle::Hex => self.data.iter().try_for_each(|b| write!(f, "{b:02x}")),
+            BytesStyle::CEscaped => {
+                f.write_str("\"")?;
+                for &b in self.data {
+                    match b {
+                        b'\n' => f.write_str("\\n")?,
+                        b'\r' => f.write_str("\\r")?,
+                        b'\t' => f.write_str("\\t")?,
+                        b'"' => f.write_str("\\\"")?,
+                        b'\'' => f.write_str("\\'")?,
+                        b'\\' => f.write_str("\\\\")?,
+                        0x20..=0x7e => write!(f, "{}", b as char)?,
+                        _ => write!(f, "\\{b:03o}")?,
+                    }
+                }
+                f.write_str("\"")
+            }
+            BytesStyle::Base64 => {
+                f.write_str(&base64::encode(self.data, base64::Alphabet::Standard))
+            }
+            BytesStyle::LengthOnly => write!(f, "<{} bytes>", self.data.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: &[u8] = b"a\"\n\x00\xff";
+
+    #[test]
+    fn test_styles() {
+        assert_eq!(bytes(DATA, BytesStyle::Hex).to_string(), "61220a00ff");
+        assert_eq!(bytes(DATA, BytesStyle::CEscaped).to_string(), r#""a\"\n\000\377""#);
+        assert_eq!(bytes(DATA, BytesStyle::Base64).to_string(), "YSIKAP8=");
+        assert_eq!(bytes(DATA, BytesStyle::LengthOnly).to_string(), "<5 bytes>");
+    }
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(bytes(b"", BytesStyle::Hex).to_string(), "");
+        assert_eq!(bytes(b"", BytesStyle::CEscaped).to_string(), r#""""#);
+    }
+}
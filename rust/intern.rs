@@ -0,0 +1,85 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Deduplication of structurally identical messages.
+
+use crate::DeterministicSerialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A pool that hands out shared handles to messages, so that structurally
+/// identical messages are only held in memory once.
+///
+/// Messages are identified by their deterministic serialization, see the
+/// generated `serialize_deterministic()`. This suits workloads holding many
+/// near-duplicate small messages; interning large messages costs a
+/// serialization each and keeps a copy of the bytes as the key.
+pub struct MessageInterner<T> {
+    pool: HashMap<Box<[u8]>, Arc<T>>,
+}
+
+impl<T: DeterministicSerialize> MessageInterner<T> {
+    pub fn new() -> Self {
+        Self { pool: HashMap::new() }
+    }
+
+    /// Returns the pooled message identical to `msg`, adding `msg` to the pool
+    /// if there is none.
+    pub fn intern(&mut self, msg: T) -> Arc<T> {
+        let key = msg.serialize_deterministic();
+        if let Some(pooled) = self.pool.get(&*key) {
+            return Arc::clone(pooled);
+        }
+        let msg = Arc::new(msg);
+        self.pool.insert(key.to_vec().into_boxed_slice(), Arc::clone(&msg));
+        msg
+    }
+
+    /// Returns the number of distinct messages in the pool.
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Returns whether the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty()
+    }
+
+    /// Drops the pooled messages that no handle outside the pool refers to.
+    pub fn purge(&mut self) {
+        self.pool.retain(|_, msg| Arc::strong_count(msg) > 1);
+    }
+}
+
+impl<T: DeterministicSerialize> Default for MessageInterner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
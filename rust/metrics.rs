@@ -0,0 +1,147 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Process-wide instrumentation of parsing and serialization.
+//!
+//! Install a [`MetricsHook`] with [`set_hook()`] to be told about every parse
+//! and serialization of a generated message, e.g. to export per-message-type
+//! cost metrics. When no hook is installed the generated code skips taking
+//! timestamps entirely.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Receives parse and serialization events for generated messages.
+///
+/// Hooks are called synchronously on the thread doing the work, so they
+/// should be cheap (e.g. bump a counter or record into a histogram).
+pub trait MetricsHook: Send + Sync {
+    /// Called after parsing `bytes` bytes into a `message` (its fully
+    /// qualified proto name), whether or not parsing succeeded.
+    fn on_parse(&self, message: &str, bytes: usize, elapsed: Duration) {
+        let _ = (message, bytes, elapsed);
+    }
+
+    /// Called after serializing a `message` (its fully qualified proto name)
+    /// into `bytes` bytes.
+    fn on_serialize(&self, message: &str, bytes: usize, elapsed: Duration) {
+        let _ = (message, bytes, elapsed);
+    }
+}
+
+static HOOK_SET: AtomicBool = AtomicBool::new(false);
+static HOOK: RwLock<Option<&'static dyn MetricsHook>> = RwLock::new(None);
+
+/// Installs `hook` for all subsequent parse and serialize calls, replacing any
+/// previously installed hook.
+pub fn set_hook(hook: &'static dyn MetricsHook) {
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = Some(hook);
+    HOOK_SET.store(true, Ordering::Release);
+}
+
+/// Removes the installed hook, if any.
+pub fn clear_hook() {
+    HOOK_SET.store(false, Ordering::Release);
+    *HOOK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+fn hook() -> Option<&'static dyn MetricsHook> {
+    if !HOOK_SET.load(Ordering::Acquire) {
+        return None;
+    }
+    *HOOK.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Runs `parse` over `bytes` bytes of input, reporting it to the installed
+/// hook.
+pub(crate) fn record_parse<T>(message: &str, bytes: usize, parse: impl FnOnce() -> T) -> T {
+    let hook = match hook() {
+        Some(hook) => hook,
+        None => return parse(),
+    };
+    let start = Instant::now();
+    let result = parse();
+    hook.on_parse(message, bytes, start.elapsed());
+    result
+}
+
+/// Runs `serialize`, which returns its result and the number of bytes it
+/// produced, reporting it to the installed hook.
+pub(crate) fn record_serialize<T>(message: &str, serialize: impl FnOnce() -> (T, usize)) -> T {
+    let hook = match hook() {
+        Some(hook) => hook,
+        None => return serialize().0,
+    };
+    let start = Instant::now();
+    let (result, bytes) = serialize();
+    hook.on_serialize(message, bytes, start.elapsed());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<(&'static str, String, usize)>>,
+    }
+
+    impl MetricsHook for Recorder {
+        fn on_parse(&self, message: &str, bytes: usize, _: Duration) {
+            self.events.lock().unwrap().push(("parse", message.to_owned(), bytes));
+        }
+
+        fn on_serialize(&self, message: &str, bytes: usize, _: Duration) {
+            self.events.lock().unwrap().push(("serialize", message.to_owned(), bytes));
+        }
+    }
+
+    // Everything touching the global hook lives in one test so that parallel
+    // tests can't observe each other's hooks.
+    #[test]
+    fn hook_receives_events() {
+        assert_eq!(record_parse("a.B", 3, || 1), 1);
+
+        let recorder: &'static Recorder = Box::leak(Box::default());
+        set_hook(recorder);
+        assert_eq!(record_parse("a.B", 3, || 2), 2);
+        assert_eq!(record_serialize("a.C", || ("out", 5)), "out");
+        clear_hook();
+        assert_eq!(record_serialize("a.C", || ("out", 5)), "out");
+
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            vec![("parse", "a.B".to_owned(), 3), ("serialize", "a.C".to_owned(), 5)]
+        );
+    }
+}
@@ -0,0 +1,121 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! The interface between generated code and the runtime.
+//!
+//! Generated code uses the runtime only through this module and the public
+//! API of the crate, never through its internals. Items here only change in
+//! compatible ways: anything generated code of a given [`GENCODE_VERSION`]
+//! relies on stays available until that version is bumped, so generated code
+//! and the runtime can be released independently.
+//!
+//! This module is not meant to be used by hand-written code.
+
+use crate::metrics;
+use crate::wire::{self, WireType};
+use crate::{Checkpoint, ParseError, ResumableSerializer, SerializeError, SerializedData};
+
+pub use crate::__runtime::Arena;
+#[cfg(cpp_kernel)]
+pub use crate::__runtime::{with_chunk_sink, ChunkSink, MAX_MESSAGE_SIZE};
+#[cfg(upb_kernel)]
+pub use crate::__runtime::{RawArena, ENCODE_OPTION_DETERMINISTIC};
+pub use crate::PtrAndLen;
+
+/// The version of the interface between generated code and this runtime.
+///
+/// Must be kept in sync with `kGencodeVersion` in the Rust code generator and
+/// bumped on any incompatible change to what generated code expects from the
+/// runtime (its thunks and the contents of this module).
+pub const GENCODE_VERSION: u32 = 2;
+
+/// Fails compilation of generated code produced for a different runtime.
+pub const fn check_gencode_version(gencode_version: u32) {
+    if gencode_version != GENCODE_VERSION {
+        panic!(
+            "This generated code is incompatible with the linked protobuf runtime. \
+             Regenerate it with a protoc matching the runtime version."
+        );
+    }
+}
+
+/// Checks that `data` is well-formed wire format in which the fields in
+/// `known_fields` have one of the wire types listed for their number.
+pub fn validate_wire(data: &[u8], known_fields: &[(u32, &[WireType])]) -> Result<(), ParseError> {
+    wire::validate_known_fields(data, known_fields)
+}
+
+/// Returns an error for `data`, which a kernel failed to parse, located as
+/// precisely as the wire format allows.
+pub fn locate_parse_error(data: &[u8]) -> ParseError {
+    ParseError::locate(data)
+}
+
+/// Returns the error for a message too large for the kernel to serialize.
+pub fn size_limit_exceeded() -> SerializeError {
+    SerializeError(())
+}
+
+/// Unwraps the result of serializing a message, for the generated
+/// serialization methods that panic on failure.
+#[track_caller]
+pub fn expect_serialized<T>(result: Result<T, SerializeError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(err) => panic!("{err}"),
+    }
+}
+
+/// Runs `parse` of message `message` over `bytes` bytes of input, reporting it
+/// to the installed metrics hook.
+pub fn record_parse<T>(message: &str, bytes: usize, parse: impl FnOnce() -> T) -> T {
+    metrics::record_parse(message, bytes, parse)
+}
+
+/// Runs `serialize` of message `message`, which returns its result and the
+/// number of bytes it produced, reporting it to the installed metrics hook.
+pub fn record_serialize<T>(message: &str, serialize: impl FnOnce() -> (T, usize)) -> T {
+    metrics::record_serialize(message, serialize)
+}
+
+/// Returns a checkpoint holding the serialized message `data`.
+pub fn new_checkpoint<M>(data: SerializedData) -> Checkpoint<M> {
+    Checkpoint::new(data)
+}
+
+/// Returns the serialized message held by `checkpoint`.
+pub fn checkpoint_data<M>(checkpoint: &Checkpoint<M>) -> &[u8] {
+    checkpoint.data()
+}
+
+/// Returns a serializer that writes out the serialized message `data`.
+pub fn new_resumable_serializer(data: SerializedData) -> ResumableSerializer {
+    ResumableSerializer::new(data)
+}
@@ -40,19 +40,239 @@ pub mod __runtime;
 #[path = "upb.rs"]
 pub mod __runtime;
 
+pub mod base64;
+pub mod confluent;
+pub mod display;
+pub mod intern;
+pub mod metrics;
+pub mod runtime_api;
+pub mod wire;
+
 pub use __runtime::SerializedData;
 
 use std::fmt;
+use std::marker::PhantomData;
 use std::slice;
 
+/// A Protobuf implementation that this runtime can be backed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    Upb,
+    Cpp,
+}
+
+/// The kernel backing this runtime, chosen with the
+/// `//rust:rust_proto_library_kernel` build setting.
+#[cfg(upb_kernel)]
+pub const KERNEL: Kernel = Kernel::Upb;
+#[cfg(cpp_kernel)]
+pub const KERNEL: Kernel = Kernel::Cpp;
+
+// Both runtimes define this symbol, so a binary that uses both fails to link
+// with a duplicate symbol error naming the problem, rather than misbehaving at
+// runtime when messages of one kernel meet code compiled for the other.
+#[used]
+#[no_mangle]
+static RUST_PROTOBUF_ERROR_BOTH_KERNELS_LINKED_INTO_ONE_BINARY: u8 = 0;
+
 /// Represents error during deserialization.
-#[derive(Debug, Clone)]
-pub struct ParseError;
+///
+/// Where the data is malformed at the wire format level, the error records
+/// the byte offset at which decoding failed and the field numbers leading
+/// there, along with a snippet of the input around that offset. Data that is
+/// well-formed on the wire but still rejected by the kernel (e.g. a missing
+/// required field) has no location.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseError {
+    offset: Option<usize>,
+    field_path: Vec<u32>,
+    snippet: Vec<u8>,
+}
+
+/// How many bytes of input `ParseError::snippet()` holds on either side of
+/// the offset.
+const SNIPPET_CONTEXT: usize = 8;
+
+impl ParseError {
+    /// An error at byte `offset` of the input.
+    pub(crate) fn at(offset: usize) -> Self {
+        Self { offset: Some(offset), field_path: Vec::new(), snippet: Vec::new() }
+    }
+
+    /// Marks the error as having occurred inside field `number`.
+    pub(crate) fn in_field(mut self, number: u32) -> Self {
+        self.field_path.insert(0, number);
+        self
+    }
+
+    /// Returns the offset into the input at which decoding failed, if known.
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Returns the numbers of the fields (outermost first) being decoded when
+    /// decoding failed. Empty if the failure was outside any field or its
+    /// location is unknown.
+    pub fn field_path(&self) -> &[u32] {
+        &self.field_path
+    }
+
+    /// Returns up to 8 bytes of the input on either side of `offset()`, so
+    /// the byte at `offset()` (if any) is at index `min(offset, 8)`. Empty if
+    /// the location is unknown.
+    pub fn snippet(&self) -> &[u8] {
+        &self.snippet
+    }
+
+    /// Returns an error for `data`, which a kernel failed to parse, located as
+    /// precisely as the wire format allows.
+    pub(crate) fn locate(data: &[u8]) -> Self {
+        let mut err = wire::fields(data).find_map(Result::err).unwrap_or_default();
+        if let Some(offset) = err.offset {
+            let start = offset.saturating_sub(SNIPPET_CONTEXT);
+            let end = data.len().min(offset + SNIPPET_CONTEXT);
+            err.snippet = data[start..end].to_vec();
+        }
+        err
+    }
+}
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Couldn't deserialize given bytes into a proto")
+        write!(f, "Couldn't deserialize given bytes into a proto")?;
+        if let Some(offset) = self.offset {
+            write!(f, " at byte {offset}")?;
+        }
+        if !self.field_path.is_empty() {
+            let path: Vec<String> = self.field_path.iter().map(u32::to_string).collect();
+            write!(f, " in field {}", path.join("."))?;
+        }
+        if !self.snippet.is_empty() {
+            let bytes: Vec<String> = self.snippet.iter().map(|b| format!("{b:02x}")).collect();
+            write!(f, " (near {})", bytes.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Represents error during serialization.
+///
+/// Returned when a message is too large for the kernel to serialize. The C++
+/// kernel can't serialize messages of 2GiB or more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerializeError(());
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Couldn't serialize the proto: it exceeds the kernel's size limit")
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+/// A snapshot of the state of a message of type `M`.
+///
+/// Taken with the generated `checkpoint()` and restored with `rollback()`,
+/// e.g. to undo a partially applied multi-field edit. The snapshot is stored
+/// serialized, so taking one costs about as much as a `serialize()` call.
+pub struct Checkpoint<M> {
+    data: SerializedData,
+    _msg: PhantomData<fn() -> M>,
+}
+
+impl<M> Checkpoint<M> {
+    pub(crate) fn new(data: SerializedData) -> Self {
+        Self { data, _msg: PhantomData }
+    }
+
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Serializes a message into caller-provided buffers of bounded size, across
+/// as many calls as it takes.
+///
+/// Created with the generated `serializer()`, for environments where output
+/// has to go out in fixed-size pieces (e.g. DMA buffers or MTU-sized frames)
+/// and the caller decides when the next piece is written. The message is
+/// serialized up front, so it can be modified while the output is drained.
+pub struct ResumableSerializer {
+    data: SerializedData,
+    pos: usize,
+}
+
+impl ResumableSerializer {
+    pub(crate) fn new(data: SerializedData) -> Self {
+        Self { data, pos: 0 }
     }
+
+    /// Writes the next piece of the serialized message to the start of `buf`
+    /// and returns its length, which is less than `buf.len()` only for the
+    /// last piece. Returns 0 once all output was written.
+    pub fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let rest = &self.data[self.pos..];
+        let len = rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        self.pos += len;
+        len
+    }
+
+    /// Returns the number of bytes not written yet.
+    pub fn remaining(&self) -> usize {
+        self.data.len() - self.pos
+    }
+
+    /// Returns whether all output was written.
+    pub fn is_done(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+/// The value of a field, as visited by the generated `fields()` iterator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldValue<'a> {
+    Int64(i64),
+    Bool(bool),
+    Bytes(&'a [u8]),
+}
+
+/// Static metadata about a field of message `M` that has generated accessors.
+///
+/// Each generated message has a `FIELDS` table of these, which allows generic
+/// traversal of set fields without descriptors.
+pub struct FieldInfo<M> {
+    pub number: u32,
+    pub name: &'static str,
+    /// Returns the value of the field, or `None` if it is not set. Fields
+    /// without presence count as set when they hold a non-default value.
+    pub get: for<'a> fn(&'a M) -> Option<FieldValue<'a>>,
+}
+
+/// Marker for owned messages that can be moved to, and dropped on, another
+/// thread.
+///
+/// Generated owned messages implement this trait on every kernel where doing
+/// so is sound, so generic async code can bound on `T: SendableMessage` and
+/// have the compiler reject configurations that would be unsound across
+/// `.await` points.
+///
+/// # Safety
+///
+/// Implementors must exclusively own all memory backing the message (for
+/// example the arena on the upb kernel) and must not hold any state tied to
+/// the thread that created them.
+pub unsafe trait SendableMessage: Send + 'static {}
+
+/// Messages that have a deterministic serialization.
+///
+/// Implemented by all generated messages, so generic code such as
+/// [`intern::MessageInterner`] can compare messages structurally.
+pub trait DeterministicSerialize {
+    /// See the generated `serialize_deterministic()`.
+    fn serialize_deterministic(&self) -> SerializedData;
 }
 
 /// Represents an ABI-stable version of &[u8]/string_view (a borrowed slice of
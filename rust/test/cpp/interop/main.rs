@@ -49,6 +49,7 @@ extern "C" {
     fn DeserializeTestAllTypes(data: *const u8, len: usize) -> NonNull<u8>;
     fn MutateTestAllTypes(msg: NonNull<u8>);
     fn SerializeTestAllTypes(msg: NonNull<u8>) -> protobuf_cpp::SerializedData;
+    fn SerializeTestAllTypesDeterministic(msg: NonNull<u8>) -> protobuf_cpp::SerializedData;
 
     fn NewWithExtension() -> NonNull<u8>;
     fn GetBytesExtension(msg: NonNull<u8>) -> protobuf_cpp::PtrAndLen;
@@ -99,6 +100,20 @@ fn deserialize_in_cpp() {
     proto_assert_eq!(msg1, msg2);
 }
 
+#[test]
+fn serialize_deterministic_matches_cpp() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(-1));
+    msg.optional_bool_set(Some(true));
+    msg.optional_bytes_set(Some(b"deterministic"));
+    let expected = unsafe {
+        SerializeTestAllTypesDeterministic(msg.__unstable_cpp_repr_grant_permission_to_break())
+    };
+
+    assert_eq!(*msg.serialize_deterministic(), *expected);
+    assert_eq!(msg.content_digest(Vec::new()).unwrap(), *expected);
+}
+
 // This test ensures that random fields we (Rust) don't know about don't
 // accidentally get destroyed by Rust.
 #[test]
@@ -115,3 +130,19 @@ fn smuggle_extension() {
     };
     assert_eq!(&*bytes, b"smuggled");
 }
+
+// The C++ kernel can't handle messages of 2GiB or more. Oversized input must
+// be rejected with an error instead of aborting the process.
+#[test]
+fn deserialize_rejects_oversized_input() {
+    let data = vec![0u8; protobuf_cpp::runtime_api::MAX_MESSAGE_SIZE + 1];
+    let mut msg = TestAllTypes::new();
+    assert!(msg.deserialize(&data).is_err());
+}
+
+#[test]
+fn try_serialize_within_size_limit() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    assert_eq!(*msg.try_serialize().unwrap(), *msg.serialize());
+}
@@ -29,8 +29,13 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 #include <cstddef>
+#include <cstring>
+#include <string>
 
+#include "absl/log/absl_check.h"
 #include "absl/strings/string_view.h"
+#include "google/protobuf/io/coded_stream.h"
+#include "google/protobuf/io/zero_copy_stream_impl_lite.h"
 #include "google/protobuf/rust/cpp_kernel/cpp_api.h"
 #include "google/protobuf/unittest.pb.h"
 
@@ -42,7 +47,27 @@ extern "C" void MutateTestAllTypes(protobuf_unittest::TestAllTypes* msg) {
 
 extern "C" google::protobuf::rust_internal::SerializedData SerializeTestAllTypes(
     const protobuf_unittest::TestAllTypes* msg) {
-  return google::protobuf::rust_internal::SerializeMsg(msg);
+  google::protobuf::rust_internal::SerializedData data(nullptr, 0);
+  ABSL_CHECK(google::protobuf::rust_internal::SerializeMsg(msg, &data));
+  return data;
+}
+
+// Serializes `msg` deterministically through the plain C++ API, independently
+// of the thunks used by the generated Rust code.
+extern "C" google::protobuf::rust_internal::SerializedData
+SerializeTestAllTypesDeterministic(const protobuf_unittest::TestAllTypes* msg) {
+  std::string out;
+  {
+    google::protobuf::io::StringOutputStream stream(&out);
+    google::protobuf::io::CodedOutputStream coded(&stream);
+    coded.SetSerializationDeterministic(true);
+    ABSL_CHECK(msg->SerializeToCodedStream(&coded));
+  }
+  void* bytes =
+      google::protobuf::rust_internal::__pb_rust_alloc(out.size(), alignof(char));
+  std::memcpy(bytes, out.data(), out.size());
+  return google::protobuf::rust_internal::SerializedData(
+      static_cast<const char*>(bytes), out.size());
 }
 
 extern "C" void* DeserializeTestAllTypes(const void* data, size_t size) {
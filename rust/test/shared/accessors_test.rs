@@ -43,6 +43,20 @@ fn test_optional_int64_accessors() {
     assert_eq!(msg.optional_int64(), None);
 }
 
+#[test]
+fn test_optional_int64_try_set() {
+    let mut msg = TestAllTypes::new();
+    assert!(msg.optional_int64_try_set(7u8).is_ok());
+    assert_eq!(msg.optional_int64(), Some(7));
+
+    assert!(msg.optional_int64_try_set(i128::from(i64::MIN)).is_ok());
+    assert_eq!(msg.optional_int64(), Some(i64::MIN));
+
+    // Out of range values are rejected and leave the field untouched.
+    assert!(msg.optional_int64_try_set(u64::MAX).is_err());
+    assert_eq!(msg.optional_int64(), Some(i64::MIN));
+}
+
 #[test]
 fn test_optional_bool_accessors() {
     let mut msg = TestAllTypes::new();
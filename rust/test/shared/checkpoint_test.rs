@@ -0,0 +1,74 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use required_fields_proto::required_fields::TestRequiredFields;
+use unittest_proto::proto2_unittest::TestAllTypes;
+
+#[test]
+fn test_rollback_restores_checkpoint() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    let checkpoint = msg.checkpoint();
+
+    msg.optional_int64_set(Some(7));
+    msg.optional_bool_set(Some(true));
+    msg.optional_bytes_set(Some(b"edited"));
+    msg.rollback(&checkpoint).unwrap();
+
+    assert_eq!(msg.optional_int64(), Some(42));
+    assert_eq!(msg.optional_bool(), None);
+    assert_eq!(msg.optional_bytes(), None);
+}
+
+#[test]
+fn test_checkpoint_can_be_reused() {
+    let mut msg = TestAllTypes::new();
+    let checkpoint = msg.checkpoint();
+
+    for i in 0..3 {
+        msg.optional_int64_set(Some(i));
+        msg.rollback(&checkpoint).unwrap();
+        assert_eq!(msg.optional_int64(), None);
+    }
+}
+
+#[test]
+fn test_checkpoint_with_unset_required_fields() {
+    let mut msg = TestRequiredFields::new();
+    msg.value_set(Some(1));
+    let checkpoint = msg.checkpoint();
+
+    msg.id_set(Some(2));
+    msg.value_set(Some(3));
+    msg.rollback(&checkpoint).unwrap();
+
+    assert_eq!(msg.id(), None);
+    assert_eq!(msg.value(), Some(1));
+}
@@ -0,0 +1,74 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use proto3_presence_proto::proto3_presence::TestProto3Presence;
+use protobuf::FieldValue;
+use unittest_proto::proto2_unittest::TestAllTypes;
+
+#[test]
+fn test_field_table() {
+    let info = TestAllTypes::FIELDS.iter().find(|f| f.name == "optional_int64").unwrap();
+    assert_eq!(info.number, 2);
+
+    let mut msg = TestAllTypes::new();
+    assert_eq!((info.get)(&msg), None);
+    msg.optional_int64_set(Some(42));
+    assert_eq!((info.get)(&msg), Some(FieldValue::Int64(42)));
+}
+
+#[test]
+fn test_fields_visits_set_fields() {
+    let mut msg = TestAllTypes::new();
+    assert_eq!(msg.fields().count(), 0);
+
+    msg.optional_int64_set(Some(42));
+    msg.optional_bool_set(Some(false));
+    msg.optional_bytes_set(Some(b"fields"));
+    assert_eq!(
+        msg.fields().collect::<Vec<_>>(),
+        vec![
+            (2, FieldValue::Int64(42)),
+            (13, FieldValue::Bool(false)),
+            (15, FieldValue::Bytes(b"fields")),
+        ]
+    );
+}
+
+#[test]
+fn test_fields_skips_implicit_defaults() {
+    let mut msg = TestProto3Presence::new();
+    msg.implicit_int64_set(0);
+    msg.implicit_bool_set(true);
+    msg.optional_int64_set(Some(0));
+    assert_eq!(
+        msg.fields().collect::<Vec<_>>(),
+        vec![(2, FieldValue::Int64(0)), (3, FieldValue::Bool(true))]
+    );
+}
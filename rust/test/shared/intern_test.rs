@@ -0,0 +1,63 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use protobuf::intern::MessageInterner;
+use std::sync::Arc;
+use unittest_proto::proto2_unittest::TestAllTypes;
+
+fn msg_with_int64(value: i64) -> TestAllTypes {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(value));
+    msg
+}
+
+#[test]
+fn intern_deduplicates_identical_messages() {
+    let mut interner = MessageInterner::new();
+    let a = interner.intern(msg_with_int64(1));
+    let b = interner.intern(msg_with_int64(1));
+    let c = interner.intern(msg_with_int64(2));
+
+    assert!(Arc::ptr_eq(&a, &b));
+    assert!(!Arc::ptr_eq(&a, &c));
+    assert_eq!(b.optional_int64(), Some(1));
+    assert_eq!(interner.len(), 2);
+}
+
+#[test]
+fn purge_drops_unreferenced_messages() {
+    let mut interner = MessageInterner::new();
+    let kept = interner.intern(msg_with_int64(1));
+    drop(interner.intern(msg_with_int64(2)));
+
+    interner.purge();
+    assert_eq!(interner.len(), 1);
+    assert!(Arc::ptr_eq(&kept, &interner.intern(msg_with_int64(1))));
+}
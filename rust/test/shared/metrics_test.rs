@@ -0,0 +1,80 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use protobuf::metrics::{self, MetricsHook};
+use std::sync::Mutex;
+use std::time::Duration;
+use unittest_proto::proto2_unittest::TestAllTypes;
+
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<(&'static str, String, usize)>>,
+}
+
+impl MetricsHook for Recorder {
+    fn on_parse(&self, message: &str, bytes: usize, _: Duration) {
+        self.events.lock().unwrap().push(("parse", message.to_owned(), bytes));
+    }
+
+    fn on_serialize(&self, message: &str, bytes: usize, _: Duration) {
+        self.events.lock().unwrap().push(("serialize", message.to_owned(), bytes));
+    }
+}
+
+// The hook is process-wide, so this is the only test in this binary.
+#[test]
+fn test_hook_sees_generated_messages() {
+    let recorder: &'static Recorder = Box::leak(Box::default());
+    metrics::set_hook(recorder);
+
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    let serialized = msg.serialize();
+    let mut out = Vec::new();
+    msg.serialize_append(&mut out);
+    msg.serialize_chunked(1, |_| {});
+    assert!(TestAllTypes::new().deserialize(&serialized).is_ok());
+    // Reports the size of the input, not of the fields kept.
+    assert!(TestAllTypes::new().deserialize_fields(&serialized, &[]).is_ok());
+    metrics::clear_hook();
+
+    let name = "proto2_unittest.TestAllTypes".to_owned();
+    let len = serialized.len();
+    assert_eq!(
+        *recorder.events.lock().unwrap(),
+        vec![
+            ("serialize", name.clone(), len),
+            ("serialize", name.clone(), len),
+            ("serialize", name.clone(), len),
+            ("parse", name.clone(), len),
+            ("parse", name, len),
+        ]
+    );
+}
@@ -0,0 +1,106 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+use proto3_presence_proto::proto3_presence::TestProto3Presence;
+
+#[test]
+fn test_implicit_presence_scalars() {
+    let mut msg = TestProto3Presence::new();
+    assert_eq!(msg.implicit_int64(), 0);
+    assert!(!msg.implicit_bool());
+
+    msg.implicit_int64_set(42);
+    msg.implicit_bool_set(true);
+    assert_eq!(msg.implicit_int64(), 42);
+    assert!(msg.implicit_bool());
+
+    // Default values are indistinguishable from unset and aren't serialized.
+    msg.implicit_int64_set(0);
+    msg.implicit_bool_set(false);
+    assert_eq!(&*msg.serialize(), b"");
+}
+
+#[test]
+fn test_implicit_presence_bytes() {
+    let mut msg = TestProto3Presence::new();
+    assert_eq!(msg.implicit_bytes(), b"");
+
+    msg.implicit_bytes_set(b"accessors_test");
+    assert_eq!(msg.implicit_bytes(), b"accessors_test");
+
+    msg.implicit_bytes_set(b"");
+    assert_eq!(&*msg.serialize(), b"");
+}
+
+#[test]
+fn test_proto3_optional_tracks_presence() {
+    let mut msg = TestProto3Presence::new();
+    assert_eq!(msg.optional_int64(), None);
+    assert_eq!(msg.optional_bool(), None);
+    assert_eq!(msg.optional_bytes(), None);
+
+    // Unlike implicit presence, explicitly setting the default is observable.
+    msg.optional_int64_set(Some(0));
+    msg.optional_bool_set(Some(false));
+    msg.optional_bytes_set(Some(b""));
+    assert_eq!(msg.optional_int64(), Some(0));
+    assert_eq!(msg.optional_bool(), Some(false));
+    assert_eq!(msg.optional_bytes(), Some(&b""[..]));
+    assert_ne!(&*msg.serialize(), b"");
+
+    msg.optional_int64_set(None);
+    msg.optional_bool_set(None);
+    msg.optional_bytes_set(None);
+    assert_eq!(msg.optional_int64(), None);
+    assert_eq!(msg.optional_bool(), None);
+    assert_eq!(msg.optional_bytes(), None);
+    assert_eq!(&*msg.serialize(), b"");
+}
+
+#[test]
+fn test_oneof_members() {
+    let mut msg = TestProto3Presence::new();
+    assert_eq!(msg.oneof_int64(), None);
+    assert_eq!(msg.oneof_bool(), None);
+
+    // Like proto3 `optional`, oneof members track presence.
+    msg.oneof_int64_set(Some(0));
+    assert_eq!(msg.oneof_int64(), Some(0));
+    assert_ne!(&*msg.serialize(), b"");
+
+    // Setting another member of the oneof clears the previous one.
+    msg.oneof_bool_set(Some(true));
+    assert_eq!(msg.oneof_int64(), None);
+    assert_eq!(msg.oneof_bool(), Some(true));
+
+    msg.oneof_bool_set(None);
+    assert_eq!(msg.oneof_bool(), None);
+    assert_eq!(&*msg.serialize(), b"");
+}
@@ -28,6 +28,8 @@
 // (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
+use empty_proto::google::protobuf::Empty;
+use protobuf::base64::Alphabet;
 use unittest_proto::proto2_unittest::TestAllTypes;
 
 #[test]
@@ -47,12 +49,184 @@ fn serialize_deserialize_message() {
     assert_eq!(msg.optional_bytes(), msg2.optional_bytes());
 }
 
+#[test]
+fn serialize_chunked_matches_serialize() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    msg.optional_bytes_set(Some(&[b'x'; 100]));
+
+    let mut chunks = Vec::new();
+    msg.serialize_chunked(16, |chunk| chunks.push(chunk.to_vec()));
+
+    assert!(chunks.iter().all(|chunk| chunk.len() <= 16));
+    assert_eq!(chunks.concat(), &*msg.serialize());
+}
+
+#[test]
+fn serialize_chunked_with_large_chunk_size() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+
+    let mut chunks = Vec::new();
+    msg.serialize_chunked(1 << 30, |chunk| chunks.push(chunk.to_vec()));
+    assert_eq!(chunks, [msg.serialize().to_vec()]);
+}
+
+#[test]
+fn serialize_chunked_propagates_sink_panics() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_bytes_set(Some(&[b'x'; 100]));
+
+    let mut calls = 0;
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        msg.serialize_chunked(16, |_| {
+            calls += 1;
+            panic!("sink failed");
+        })
+    }));
+    assert_eq!(*result.unwrap_err().downcast::<&str>().unwrap(), "sink failed");
+    assert_eq!(calls, 1);
+}
+
+#[test]
+fn serializer_fills_bounded_buffers() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    msg.optional_bytes_set(Some(&[b'x'; 100]));
+
+    let mut serializer = msg.serializer();
+    // Changes after creating the serializer don't affect its output.
+    let expected = msg.serialize().to_vec();
+    msg.optional_int64_set(None);
+
+    let mut out = Vec::new();
+    let mut buf = [0; 16];
+    while !serializer.is_done() {
+        let len = serializer.fill(&mut buf);
+        out.extend_from_slice(&buf[..len]);
+        assert_eq!(serializer.remaining(), expected.len() - out.len());
+    }
+    assert_eq!(out, expected);
+    assert_eq!(serializer.fill(&mut buf), 0);
+}
+
+#[test]
+fn serialize_into_reuses_buffer() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    msg.optional_bytes_set(Some(b"serialize into test"));
+
+    let mut out = b"stale".to_vec();
+    msg.serialize_into(&mut out);
+    assert_eq!(out, &*msg.serialize());
+
+    let capacity = out.capacity();
+    msg.optional_bytes_set(None);
+    msg.serialize_into(&mut out);
+    assert_eq!(out, &*msg.serialize());
+    assert_eq!(out.capacity(), capacity);
+}
+
+#[test]
+fn serialize_append() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_bool_set(Some(true));
+
+    let mut out = b"prefix".to_vec();
+    msg.serialize_append(&mut out);
+    assert_eq!(out, [&b"prefix"[..], &msg.serialize()].concat());
+}
+
+#[test]
+fn serialize_deterministic() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    msg.optional_bytes_set(Some(b"deterministic"));
+
+    // Without map fields, the deterministic encoding matches the regular one.
+    assert_eq!(*msg.serialize_deterministic(), *msg.serialize());
+}
+
+#[test]
+fn content_digest() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+
+    // Any `io::Write` can act as the hasher; a `Vec` just collects the input.
+    let digested = msg.content_digest(Vec::new()).unwrap();
+    assert_eq!(digested, *msg.serialize_deterministic());
+}
+
+#[test]
+fn base64_roundtrip() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+
+    for alphabet in [Alphabet::Standard, Alphabet::UrlSafe] {
+        let text = msg.serialize_base64(alphabet);
+        let mut msg2 = TestAllTypes::new();
+        assert!(msg2.deserialize_base64(&text, alphabet).is_ok());
+        assert_eq!(msg2.optional_int64(), Some(42));
+    }
+
+    assert!(TestAllTypes::new().deserialize_base64("not base64!", Alphabet::UrlSafe).is_err());
+}
+
 #[test]
 fn deserialize_empty() {
     let mut msg = TestAllTypes::new();
     assert!(msg.deserialize(&[]).is_ok());
 }
 
+#[test]
+fn default_serializes_empty() {
+    let msg = TestAllTypes::default();
+    assert!(msg.serialize().is_empty());
+}
+
+#[test]
+fn empty_from_unit() {
+    let msg: Empty = ().into();
+    assert!(msg.serialize().is_empty());
+    assert!(Empty::from(()).serialize().is_empty());
+}
+
+#[test]
+fn deserialize_selected_fields() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    msg.optional_bool_set(Some(true));
+    msg.optional_bytes_set(Some(b"skipped"));
+    let serialized = msg.serialize();
+
+    let mut msg2 = TestAllTypes::new();
+    // optional_int64 is field 2.
+    assert!(msg2.deserialize_fields(&serialized, &[2]).is_ok());
+
+    assert_eq!(msg2.optional_int64(), Some(42));
+    assert_eq!(msg2.optional_bool(), None);
+    assert_eq!(msg2.optional_bytes(), None);
+}
+
+#[test]
+fn validate_wire_checks_wire_types() {
+    let mut msg = TestAllTypes::new();
+    msg.optional_int64_set(Some(42));
+    msg.optional_bytes_set(Some(b"valid"));
+    assert!(TestAllTypes::validate_wire(&msg.serialize()).is_ok());
+
+    // optional_int64 (field 2) encoded as length-delimited.
+    let err = TestAllTypes::validate_wire(&[0x12, 0x00]).unwrap_err();
+    assert_eq!((err.offset(), err.field_path()), (Some(0), &[2][..]));
+
+    // repeated_int32 (field 31) may be packed or not.
+    assert!(TestAllTypes::validate_wire(&[0xfa, 0x01, 0x01, 0x05]).is_ok());
+    assert!(TestAllTypes::validate_wire(&[0xf8, 0x01, 0x05]).is_ok());
+
+    // Truncated input.
+    assert!(TestAllTypes::validate_wire(&[0x10]).is_err());
+}
+
 #[test]
 fn deserialize_error() {
     let mut msg = TestAllTypes::new();
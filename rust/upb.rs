@@ -32,7 +32,7 @@
 
 use std::alloc;
 use std::alloc::Layout;
-use std::cell::UnsafeCell;
+use std::cell::{Cell, UnsafeCell};
 use std::fmt;
 use std::marker::PhantomData;
 use std::mem::MaybeUninit;
@@ -43,6 +43,9 @@ use std::slice;
 /// See `upb/port/def.inc`.
 const UPB_MALLOC_ALIGN: usize = 8;
 
+/// `kUpb_EncodeOption_Deterministic`, see `upb/wire/encode.h`.
+pub const ENCODE_OPTION_DETERMINISTIC: i32 = 1;
+
 /// A UPB-managed pointer to a raw arena.
 pub type RawArena = NonNull<RawArenaData>;
 
@@ -63,21 +66,126 @@ pub struct RawArenaData {
 /// Note that this type is neither `Sync` nor `Send`.
 pub struct Arena {
     raw: RawArena,
+    wrapper_alloc_count: Cell<usize>,
+    wrapper_alloc_bytes: Cell<usize>,
     _not_sync: PhantomData<UnsafeCell<()>>,
 }
 
 extern "C" {
+    #[cfg(not(upb_rust_alloc))]
     fn upb_Arena_New() -> RawArena;
+    #[cfg(upb_rust_alloc)]
+    fn upb_Arena_Init(mem: *mut u8, n: usize, alloc: *const UpbAlloc) -> RawArena;
     fn upb_Arena_Free(arena: RawArena);
     fn upb_Arena_Malloc(arena: RawArena, size: usize) -> *mut u8;
     fn upb_Arena_Realloc(arena: RawArena, ptr: *mut u8, old: usize, new: usize) -> *mut u8;
+    fn upb_Arena_SpaceAllocated(arena: RawArena) -> usize;
+}
+
+/// A snapshot of the memory usage of an [`Arena`].
+///
+/// Returned by `Arena::stats()`; servers can export these numbers as metrics
+/// or use them to detect bloat in long-lived arenas.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArenaStats {
+    /// Total bytes the arena obtained from the underlying allocator, including
+    /// block overhead and capacity not yet handed out. This covers all of the
+    /// arena's memory, including what upb allocated itself while parsing.
+    pub space_allocated: usize,
+    /// Number of allocations made through `Arena::alloc()` and
+    /// `Arena::resize()`. Allocations upb makes internally are not counted.
+    pub wrapper_alloc_count: usize,
+    /// Total bytes requested through `Arena::alloc()` and `Arena::resize()`.
+    /// Allocations upb makes internally are not counted.
+    pub wrapper_alloc_bytes: usize,
+}
+
+/// Mirrors `upb_alloc` from `upb/mem/alloc.h`.
+#[cfg(upb_rust_alloc)]
+#[repr(C)]
+struct UpbAlloc {
+    func: unsafe extern "C" fn(*const UpbAlloc, *mut u8, usize, usize) -> *mut u8,
+}
+
+/// Routes upb's block allocations through the Rust global allocator, so that
+/// whatever `#[global_allocator]` the binary uses also sees protobuf memory.
+///
+/// Enabled with `--//rust:use_rust_allocator_for_upb`.
+#[cfg(upb_rust_alloc)]
+static RUST_UPB_ALLOC: UpbAlloc = UpbAlloc { func: rust_upb_alloc };
+
+/// Size of the header `rust_upb_alloc` prepends to every block. upb does not
+/// pass the block size when freeing, so it has to be stored in the block.
+#[cfg(upb_rust_alloc)]
+const RUST_UPB_ALLOC_HEADER: usize = UPB_MALLOC_ALIGN;
+
+/// Implements `upb_alloc_func` on top of `std::alloc`.
+///
+/// Follows the `upb_alloc_func` contract: a null `ptr` allocates, a zero
+/// `size` frees, anything else reallocates.
+#[cfg(upb_rust_alloc)]
+unsafe extern "C" fn rust_upb_alloc(
+    _alloc: *const UpbAlloc,
+    ptr: *mut u8,
+    _old: usize,
+    size: usize,
+) -> *mut u8 {
+    // Returns `None` for sizes that can't be allocated, which upb sees as an
+    // allocation failure. Nothing in here may panic: unwinding out of this
+    // callback would cross C frames.
+    let layout_for = |size: usize| {
+        let size = size.checked_add(RUST_UPB_ALLOC_HEADER)?;
+        Layout::from_size_align(size, UPB_MALLOC_ALIGN).ok()
+    };
+
+    let block = if ptr.is_null() {
+        if size == 0 {
+            return std::ptr::null_mut();
+        }
+        match layout_for(size) {
+            Some(layout) => alloc::alloc(layout),
+            None => return std::ptr::null_mut(),
+        }
+    } else {
+        let block = ptr.sub(RUST_UPB_ALLOC_HEADER);
+        // The stored size was accepted by `layout_for` when the block was
+        // allocated, so this only fails if the header was overwritten.
+        let old = match layout_for(block.cast::<usize>().read()) {
+            Some(old) => old,
+            None => return std::ptr::null_mut(),
+        };
+        if size == 0 {
+            alloc::dealloc(block, old);
+            return std::ptr::null_mut();
+        }
+        match layout_for(size) {
+            Some(new) => alloc::realloc(block, old, new.size()),
+            None => return std::ptr::null_mut(),
+        }
+    };
+
+    if block.is_null() {
+        return block;
+    }
+    block.cast::<usize>().write(size);
+    block.add(RUST_UPB_ALLOC_HEADER)
 }
 
 impl Arena {
     /// Allocates a fresh arena.
     #[inline]
     pub fn new() -> Self {
-        Self { raw: unsafe { upb_Arena_New() }, _not_sync: PhantomData }
+        #[cfg(not(upb_rust_alloc))]
+        let raw = unsafe { upb_Arena_New() };
+        #[cfg(upb_rust_alloc)]
+        let raw = unsafe { upb_Arena_Init(std::ptr::null_mut(), 0, &RUST_UPB_ALLOC) };
+
+        Self {
+            raw,
+            wrapper_alloc_count: Cell::new(0),
+            wrapper_alloc_bytes: Cell::new(0),
+            _not_sync: PhantomData,
+        }
     }
 
     /// Returns the raw, UPB-managed pointer to the arena.
@@ -86,6 +194,29 @@ impl Arena {
         self.raw
     }
 
+    /// Returns the total number of bytes this arena has obtained from the
+    /// underlying allocator, including block overhead and unused capacity.
+    #[inline]
+    pub fn space_allocated(&self) -> usize {
+        unsafe { upb_Arena_SpaceAllocated(self.raw) }
+    }
+
+    /// Returns a snapshot of this arena's memory usage.
+    #[inline]
+    pub fn stats(&self) -> ArenaStats {
+        ArenaStats {
+            space_allocated: self.space_allocated(),
+            wrapper_alloc_count: self.wrapper_alloc_count.get(),
+            wrapper_alloc_bytes: self.wrapper_alloc_bytes.get(),
+        }
+    }
+
+    #[inline]
+    fn record_alloc(&self, size: usize) {
+        self.wrapper_alloc_count.set(self.wrapper_alloc_count.get() + 1);
+        self.wrapper_alloc_bytes.set(self.wrapper_alloc_bytes.get() + size);
+    }
+
     /// Allocates some memory on the arena.
     ///
     /// # Safety
@@ -98,6 +229,7 @@ impl Arena {
         if ptr.is_null() {
             alloc::handle_alloc_error(layout);
         }
+        self.record_alloc(layout.size());
 
         slice::from_raw_parts_mut(ptr.cast(), layout.size())
     }
@@ -116,6 +248,7 @@ impl Arena {
         if ptr.is_null() {
             alloc::handle_alloc_error(new);
         }
+        self.record_alloc(new.size());
 
         slice::from_raw_parts_mut(ptr.cast(), new.size())
     }
@@ -170,6 +303,65 @@ mod tests {
         drop(arena);
     }
 
+    #[test]
+    fn test_arena_space_allocated_grows() {
+        let arena = Arena::new();
+        let before = arena.space_allocated();
+        unsafe {
+            arena.alloc(Layout::from_size_align(4096, 8).unwrap());
+        }
+        assert!(arena.space_allocated() >= before + 4096);
+    }
+
+    #[test]
+    fn test_arena_stats() {
+        let arena = Arena::new();
+        assert_eq!(arena.stats().wrapper_alloc_count, 0);
+
+        let old = Layout::from_size_align(16, 8).unwrap();
+        let new = Layout::from_size_align(32, 8).unwrap();
+        unsafe {
+            let ptr = arena.alloc(old).as_mut_ptr();
+            arena.resize(ptr.cast(), old, new);
+        }
+
+        let stats = arena.stats();
+        assert_eq!(stats.wrapper_alloc_count, 2);
+        assert_eq!(stats.wrapper_alloc_bytes, 48);
+        assert!(stats.space_allocated >= 32);
+    }
+
+    #[cfg(upb_rust_alloc)]
+    #[test]
+    fn test_rust_upb_alloc() {
+        unsafe {
+            let ptr = rust_upb_alloc(&RUST_UPB_ALLOC, std::ptr::null_mut(), 0, 16);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % UPB_MALLOC_ALIGN, 0);
+            ptr.write_bytes(0xAB, 16);
+
+            let ptr = rust_upb_alloc(&RUST_UPB_ALLOC, ptr, 16, 64);
+            assert!(!ptr.is_null());
+            assert_eq!(*ptr.add(15), 0xAB);
+
+            assert!(rust_upb_alloc(&RUST_UPB_ALLOC, ptr, 0, 0).is_null());
+        }
+    }
+
+    #[cfg(upb_rust_alloc)]
+    #[test]
+    fn test_rust_upb_alloc_rejects_oversized_blocks() {
+        unsafe {
+            let huge = usize::MAX - RUST_UPB_ALLOC_HEADER / 2;
+            assert!(rust_upb_alloc(&RUST_UPB_ALLOC, std::ptr::null_mut(), 0, huge).is_null());
+
+            let ptr = rust_upb_alloc(&RUST_UPB_ALLOC, std::ptr::null_mut(), 0, 16);
+            assert!(!ptr.is_null());
+            assert!(rust_upb_alloc(&RUST_UPB_ALLOC, ptr, 16, huge).is_null());
+            assert!(rust_upb_alloc(&RUST_UPB_ALLOC, ptr, 0, 0).is_null());
+        }
+    }
+
     #[test]
     fn test_serialized_data_roundtrip() {
         let arena = Arena::new();
@@ -0,0 +1,791 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google Inc.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google Inc. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+//! Kernel-agnostic access to serialized Protobuf wire format data.
+//!
+//! Nothing in this module needs a message or a descriptor; it operates on
+//! field numbers and wire types only.
+
+use crate::ParseError;
+use std::io;
+
+/// Maximum nesting of groups, matching the kernels' default recursion limit.
+const MAX_DEPTH: usize = 100;
+
+/// The largest valid field number.
+const MAX_FIELD_NUMBER: u64 = (1 << 29) - 1;
+
+/// How many bytes of elements `PackedWriter` buffers before writing them out.
+const PACKED_BLOCK_SIZE: usize = 4096;
+
+/// The wire type of a field, stored in the low 3 bits of its tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireType {
+    Varint,
+    Fixed64,
+    Delimited,
+    StartGroup,
+    EndGroup,
+    Fixed32,
+}
+
+impl WireType {
+    fn from_tag(tag: u64) -> Option<Self> {
+        Some(match tag & 7 {
+            0 => WireType::Varint,
+            1 => WireType::Fixed64,
+            2 => WireType::Delimited,
+            3 => WireType::StartGroup,
+            4 => WireType::EndGroup,
+            5 => WireType::Fixed32,
+            _ => return None,
+        })
+    }
+}
+
+/// A single field record in serialized data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Field<'a> {
+    pub number: u32,
+    pub wire_type: WireType,
+    /// The payload of the record: the varint or fixed-width bytes, the
+    /// contents of a length-delimited field, or the contents of a group
+    /// (without its end tag).
+    pub value: &'a [u8],
+    /// The whole record, including its tag (and the end tag of a group).
+    pub record: &'a [u8],
+    /// The offset of `record` from the start of the data being read.
+    pub offset: usize,
+}
+
+impl<'a> Field<'a> {
+    /// Returns the value of a varint field.
+    pub fn as_varint(&self) -> Option<u64> {
+        match self.wire_type {
+            WireType::Varint => read_varint(self.value, 0).ok().map(|(value, _)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a fixed32 field.
+    pub fn as_fixed32(&self) -> Option<u32> {
+        match self.wire_type {
+            WireType::Fixed32 => self.value.try_into().ok().map(u32::from_le_bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a fixed64 field.
+    pub fn as_fixed64(&self) -> Option<u64> {
+        match self.wire_type {
+            WireType::Fixed64 => self.value.try_into().ok().map(u64::from_le_bytes),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a length-delimited field.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self.wire_type {
+            WireType::Delimited => Some(self.value),
+            _ => None,
+        }
+    }
+
+    /// Returns the contents of a length-delimited field if they are valid
+    /// UTF-8.
+    pub fn as_str(&self) -> Option<&'a str> {
+        std::str::from_utf8(self.as_bytes()?).ok()
+    }
+}
+
+/// Iterator over the top-level fields of serialized data.
+///
+/// Yields an error and then stops if the data is malformed.
+pub struct Fields<'a> {
+    data: &'a [u8],
+    pos: usize,
+    failed: bool,
+}
+
+/// Returns an iterator over the top-level fields of `data`.
+pub fn fields(data: &[u8]) -> Fields<'_> {
+    Fields { data, pos: 0, failed: false }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Result<Field<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.pos == self.data.len() {
+            return None;
+        }
+        let field = read_field(self.data, self.pos, 0);
+        match field {
+            Ok(field) => self.pos += field.record.len(),
+            Err(_) => self.failed = true,
+        }
+        Some(field)
+    }
+}
+
+/// Reads individual fields out of serialized data without parsing it into a
+/// message.
+///
+/// Useful for looking at a key field of an otherwise opaque payload, for
+/// example when routing it.
+#[derive(Debug, Clone, Copy)]
+pub struct Scanner<'a> {
+    data: &'a [u8],
+}
+
+/// Returns a [`Scanner`] over `data`.
+pub fn scan(data: &[u8]) -> Scanner<'_> {
+    Scanner { data }
+}
+
+impl<'a> Scanner<'a> {
+    /// Returns the occurrence of field `number` that a parser would keep for a
+    /// singular field, which is the last one.
+    ///
+    /// Fails if the data is malformed anywhere, not just around the field,
+    /// because the last occurrence is only known once all data was read.
+    pub fn get_field(&self, number: u32) -> Result<Option<Field<'a>>, ParseError> {
+        let mut last = None;
+        for field in fields(self.data) {
+            let field = field?;
+            if field.number == number {
+                last = Some(field);
+            }
+        }
+        Ok(last)
+    }
+
+    /// Returns all occurrences of field `number`, in order.
+    pub fn get_repeated(&self, number: u32) -> Result<Vec<Field<'a>>, ParseError> {
+        fields(self.data).filter(|f| f.as_ref().map_or(true, |f| f.number == number)).collect()
+    }
+}
+
+/// Incrementally writes the elements of a repeated field to `W`.
+///
+/// The output is a valid serialized message containing only that field, so
+/// exporters can stream a repeated field of arbitrary size (for example the
+/// records of a batch) without holding the whole collection in memory. It can
+/// also be appended to the serialization of the message's other fields.
+///
+/// Elements are written unpacked, which parsers accept for packed fields as
+/// well. Use `PackedWriter` for the smaller packed encoding.
+pub struct RepeatedWriter<W> {
+    out: W,
+    field_number: u32,
+}
+
+impl<W: io::Write> RepeatedWriter<W> {
+    /// Creates a writer for the repeated field `field_number`.
+    ///
+    /// Panics if `field_number` is not a valid field number.
+    pub fn new(out: W, field_number: u32) -> Self {
+        assert!(
+            field_number != 0 && u64::from(field_number) <= MAX_FIELD_NUMBER,
+            "invalid field number {field_number}"
+        );
+        Self { out, field_number }
+    }
+
+    /// Writes one element of a message, `bytes` or `string` field.
+    /// `element` is the serialized submessage or the raw field data.
+    pub fn write_delimited(&mut self, element: &[u8]) -> io::Result<()> {
+        self.write_tag(WireType::Delimited)?;
+        write_varint(&mut self.out, element.len() as u64)?;
+        self.out.write_all(element)
+    }
+
+    /// Writes one element of an `int32`, `int64`, `uint32`, `uint64`,
+    /// `bool` or enum field. Negative `int32` and enum values are
+    /// sign-extended to 64 bits first, as with `value as i64 as u64`.
+    pub fn write_varint(&mut self, element: u64) -> io::Result<()> {
+        self.write_tag(WireType::Varint)?;
+        write_varint(&mut self.out, element)
+    }
+
+    /// Writes one element of a `sint32` or `sint64` field.
+    pub fn write_sint(&mut self, element: i64) -> io::Result<()> {
+        self.write_varint(zigzag(element))
+    }
+
+    /// Writes one element of a `fixed32`, `sfixed32` or `float` field, as
+    /// with `value as u32` or `value.to_bits()`.
+    pub fn write_fixed32(&mut self, element: u32) -> io::Result<()> {
+        self.write_tag(WireType::Fixed32)?;
+        self.out.write_all(&element.to_le_bytes())
+    }
+
+    /// Writes one element of a `fixed64`, `sfixed64` or `double` field, as
+    /// with `value as u64` or `value.to_bits()`.
+    pub fn write_fixed64(&mut self, element: u64) -> io::Result<()> {
+        self.write_tag(WireType::Fixed64)?;
+        self.out.write_all(&element.to_le_bytes())
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn write_tag(&mut self, wire_type: WireType) -> io::Result<()> {
+        write_tag(&mut self.out, self.field_number, wire_type)
+    }
+}
+
+/// Incrementally writes the elements of a packed repeated scalar field to `W`.
+///
+/// Like `RepeatedWriter`, but uses the packed encoding. Since a packed record
+/// is prefixed with its length, elements are buffered and written out as a
+/// separate packed record every few KiB; parsers concatenate the records.
+/// Call `finish()` to write the remaining elements; dropping the writer
+/// discards them.
+///
+/// All elements must be of the same kind, as the field has a single type.
+pub struct PackedWriter<W> {
+    out: W,
+    field_number: u32,
+    buf: Vec<u8>,
+}
+
+impl<W: io::Write> PackedWriter<W> {
+    /// Creates a writer for the packed repeated field `field_number`.
+    ///
+    /// Panics if `field_number` is not a valid field number.
+    pub fn new(out: W, field_number: u32) -> Self {
+        assert!(
+            field_number != 0 && u64::from(field_number) <= MAX_FIELD_NUMBER,
+            "invalid field number {field_number}"
+        );
+        Self { out, field_number, buf: Vec::new() }
+    }
+
+    /// Like `RepeatedWriter::write_varint()`.
+    pub fn write_varint(&mut self, element: u64) -> io::Result<()> {
+        write_varint(&mut self.buf, element)?;
+        self.flush_full_block()
+    }
+
+    /// Like `RepeatedWriter::write_sint()`.
+    pub fn write_sint(&mut self, element: i64) -> io::Result<()> {
+        self.write_varint(zigzag(element))
+    }
+
+    /// Like `RepeatedWriter::write_fixed32()`.
+    pub fn write_fixed32(&mut self, element: u32) -> io::Result<()> {
+        self.buf.extend_from_slice(&element.to_le_bytes());
+        self.flush_full_block()
+    }
+
+    /// Like `RepeatedWriter::write_fixed64()`.
+    pub fn write_fixed64(&mut self, element: u64) -> io::Result<()> {
+        self.buf.extend_from_slice(&element.to_le_bytes());
+        self.flush_full_block()
+    }
+
+    /// Writes the buffered elements and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.flush_block()?;
+        Ok(self.out)
+    }
+
+    fn flush_full_block(&mut self) -> io::Result<()> {
+        if self.buf.len() >= PACKED_BLOCK_SIZE {
+            self.flush_block()?;
+        }
+        Ok(())
+    }
+
+    fn flush_block(&mut self) -> io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        write_tag(&mut self.out, self.field_number, WireType::Delimited)?;
+        write_varint(&mut self.out, self.buf.len() as u64)?;
+        self.out.write_all(&self.buf)?;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+fn write_tag(out: &mut impl io::Write, field_number: u32, wire_type: WireType) -> io::Result<()> {
+    write_varint(out, u64::from(field_number) << 3 | wire_type as u64)
+}
+
+/// ZigZag-encodes `value`, as used by `sint32` and `sint64` fields.
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Writes `value` as a varint.
+pub(crate) fn write_varint(out: &mut impl io::Write, mut value: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        buf[len] = (value & 0x7f) as u8;
+        value >>= 7;
+        len += 1;
+        if value == 0 {
+            break;
+        }
+        buf[len - 1] |= 0x80;
+    }
+    out.write_all(&buf[..len])
+}
+
+/// Decodes a varint starting at `pos`, returning it along with the position
+/// just past it.
+pub(crate) fn read_varint(data: &[u8], start: usize) -> Result<(u64, usize), ParseError> {
+    let mut value = 0u64;
+    let mut pos = start;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(pos).ok_or_else(|| ParseError::at(pos))?;
+        pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value, pos));
+        }
+    }
+    // Longer than the 10 bytes any 64-bit value fits in.
+    Err(ParseError::at(start))
+}
+
+/// Reads a tag at `pos`, returning the field number, wire type and the
+/// position just past the tag.
+fn read_tag(data: &[u8], start: usize) -> Result<(u32, WireType, usize), ParseError> {
+    let (tag, pos) = read_varint(data, start)?;
+    let number = tag >> 3;
+    if number == 0 || number > MAX_FIELD_NUMBER {
+        return Err(ParseError::at(start));
+    }
+    let wire_type = WireType::from_tag(tag).ok_or_else(|| ParseError::at(start))?;
+    Ok((number as u32, wire_type, pos))
+}
+
+/// Returns `len` bytes starting at `pos`, if there are that many.
+fn take(data: &[u8], pos: usize, len: u64) -> Result<&[u8], ParseError> {
+    let end = usize::try_from(len).ok().and_then(|len| pos.checked_add(len));
+    end.and_then(|end| data.get(pos..end)).ok_or_else(|| ParseError::at(data.len()))
+}
+
+/// Reads the field record starting at `start`. `depth` is the number of
+/// groups the record is nested in.
+fn read_field(data: &[u8], start: usize, depth: usize) -> Result<Field<'_>, ParseError> {
+    let (number, wire_type, pos) = read_tag(data, start)?;
+    if wire_type == WireType::EndGroup {
+        // An end tag without a matching start tag.
+        return Err(ParseError::at(start));
+    }
+    let (value, end) =
+        read_value(data, number, wire_type, pos, depth).map_err(|e| e.in_field(number))?;
+    Ok(Field { number, wire_type, value, record: &data[start..end], offset: start })
+}
+
+/// Reads the value of a field with the given tag, which starts at `pos`,
+/// returning it and the position just past the record.
+fn read_value(
+    data: &[u8],
+    number: u32,
+    wire_type: WireType,
+    pos: usize,
+    depth: usize,
+) -> Result<(&[u8], usize), ParseError> {
+    Ok(match wire_type {
+        WireType::Varint => {
+            let (_, end) = read_varint(data, pos)?;
+            (&data[pos..end], end)
+        }
+        WireType::Fixed64 => {
+            let value = take(data, pos, 8)?;
+            (value, pos + 8)
+        }
+        WireType::Fixed32 => {
+            let value = take(data, pos, 4)?;
+            (value, pos + 4)
+        }
+        WireType::Delimited => {
+            let (len, pos) = read_varint(data, pos)?;
+            let value = take(data, pos, len)?;
+            (value, pos + value.len())
+        }
+        WireType::StartGroup => {
+            if depth >= MAX_DEPTH {
+                return Err(ParseError::at(pos));
+            }
+            let mut end = pos;
+            loop {
+                if let Ok((end_number, WireType::EndGroup, after)) = read_tag(data, end) {
+                    if end_number != number {
+                        return Err(ParseError::at(end));
+                    }
+                    break (&data[pos..end], after);
+                }
+                end += read_field(data, end, depth + 1)?.record.len();
+            }
+        }
+        WireType::EndGroup => unreachable!("end tags are rejected by read_field()"),
+    })
+}
+
+/// Checks that `data` is well-formed wire format without building a message.
+///
+/// This checks tags, field numbers, lengths and group nesting (up to the
+/// kernels' recursion limit), which is enough to cheaply reject garbage before
+/// queueing it. It does not check the data against a schema: the contents of
+/// length-delimited fields are not inspected, so malformed submessages and
+/// invalid UTF-8 in strings are only caught by a full parse.
+pub fn validate(data: &[u8]) -> Result<(), ParseError> {
+    fields(data).try_for_each(|f| f.map(|_| ()))
+}
+
+/// Like `validate()`, but also checks that each top-level field listed in
+/// `known_fields` has one of the wire types given for its number.
+pub(crate) fn validate_known_fields(
+    data: &[u8],
+    known_fields: &[(u32, &[WireType])],
+) -> Result<(), ParseError> {
+    for field in fields(data) {
+        let field = field?;
+        let known = known_fields.iter().find(|(number, _)| *number == field.number);
+        if let Some((_, wire_types)) = known {
+            if !wire_types.contains(&field.wire_type) {
+                return Err(ParseError::at(field.offset).in_field(field.number));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns a copy of `data` containing only the top-level fields whose
+/// numbers are in `field_numbers`, in their original order.
+pub fn retain_fields(data: &[u8], field_numbers: &[u32]) -> Result<Vec<u8>, ParseError> {
+    let mut out = Vec::new();
+    for field in fields(data) {
+        let field = field?;
+        if field_numbers.contains(&field.number) {
+            out.extend_from_slice(field.record);
+        }
+    }
+    Ok(out)
+}
+
+/// How much of a serialized message a top-level field takes up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldStats {
+    pub number: u32,
+    /// The number of records of the field. For a repeated field this is the
+    /// element count, unless it is packed.
+    pub count: usize,
+    /// The total size of the field's records, including tags and lengths.
+    pub bytes: usize,
+}
+
+/// Returns the size breakdown of the top-level fields of `data`, ordered by
+/// field number, to find the fields that bloat a payload.
+///
+/// Pass the serialization of a message to break down a message instance.
+pub fn field_stats(data: &[u8]) -> Result<Vec<FieldStats>, ParseError> {
+    let mut stats: Vec<FieldStats> = Vec::new();
+    for field in fields(data) {
+        let field = field?;
+        match stats.binary_search_by_key(&field.number, |s| s.number) {
+            Ok(i) => {
+                stats[i].count += 1;
+                stats[i].bytes += field.record.len();
+            }
+            Err(i) => stats.insert(
+                i,
+                FieldStats { number: field.number, count: 1, bytes: field.record.len() },
+            ),
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Field 1: varint 150, field 2: "hi", field 3: group { field 4: fixed32 },
+    // field 5: fixed64.
+    const DATA: &[u8] = &[
+        0x08, 0x96, 0x01, //
+        0x12, 0x02, b'h', b'i', //
+        0x1b, 0x25, 1, 0, 0, 0, 0x1c, //
+        0x29, 1, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn test_read_varint() {
+        assert_eq!(read_varint(&[0x96, 0x01], 0).unwrap(), (150, 2));
+        assert_eq!(read_varint(&[0xff; 10], 0).ok(), None);
+        assert_eq!(read_varint(&[0x80], 0).ok(), None);
+    }
+
+    #[test]
+    fn test_write_varint() {
+        for value in [0, 1, 127, 128, 150, u64::from(u32::MAX), u64::MAX] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, value).unwrap();
+            assert_eq!(read_varint(&buf, 0).unwrap(), (value, buf.len()));
+        }
+    }
+
+    #[test]
+    fn test_repeated_writer() {
+        let mut writer = RepeatedWriter::new(Vec::new(), 2);
+        writer.write_delimited(b"hi").unwrap();
+        writer.write_delimited(b"").unwrap();
+        let out = writer.into_inner();
+        assert_eq!(out, [0x12, 0x02, b'h', b'i', 0x12, 0x00]);
+
+        let elements: Vec<_> =
+            scan(&out).get_repeated(2).unwrap().iter().map(|f| f.as_bytes().unwrap()).collect();
+        assert_eq!(elements, [&b"hi"[..], &b""[..]]);
+
+        let mut writer = RepeatedWriter::new(Vec::new(), 1);
+        writer.write_varint(150).unwrap();
+        assert_eq!(writer.into_inner(), [0x08, 0x96, 0x01]);
+
+        let mut writer = RepeatedWriter::new(Vec::new(), 1);
+        writer.write_sint(-1).unwrap();
+        writer.write_sint(1).unwrap();
+        writer.write_fixed32(1).unwrap();
+        writer.write_fixed64(2).unwrap();
+        assert_eq!(
+            writer.into_inner(),
+            [0x08, 0x01, 0x08, 0x02, 0x0d, 1, 0, 0, 0, 0x09, 2, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_packed_writer() {
+        let mut writer = PackedWriter::new(Vec::new(), 4);
+        writer.write_varint(3).unwrap();
+        writer.write_varint(270).unwrap();
+        writer.write_sint(-2).unwrap();
+        assert_eq!(writer.finish().unwrap(), [0x22, 0x04, 0x03, 0x8e, 0x02, 0x03]);
+
+        let writer = PackedWriter::new(Vec::new(), 4);
+        assert_eq!(writer.finish().unwrap(), []);
+
+        // Splits large fields into several packed records.
+        let mut writer = PackedWriter::new(Vec::new(), 1);
+        for i in 0..2000 {
+            writer.write_fixed32(i).unwrap();
+        }
+        let out = writer.finish().unwrap();
+        let records = scan(&out).get_repeated(1).unwrap();
+        assert_eq!(records.len(), 2);
+        let elements: Vec<u32> = records
+            .iter()
+            .flat_map(|f| f.as_bytes().unwrap().chunks(4))
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(elements, (0..2000).collect::<Vec<_>>());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_repeated_writer_invalid_field_number() {
+        RepeatedWriter::new(Vec::new(), 0);
+    }
+
+    #[test]
+    fn test_fields() {
+        let fields = fields(DATA).collect::<Result<Vec<_>, _>>().unwrap();
+        let summary: Vec<_> = fields.iter().map(|f| (f.number, f.wire_type, f.offset)).collect();
+        assert_eq!(
+            summary,
+            [
+                (1, WireType::Varint, 0),
+                (2, WireType::Delimited, 3),
+                (3, WireType::StartGroup, 7),
+                (5, WireType::Fixed64, 14),
+            ]
+        );
+        assert_eq!(fields[1].value, b"hi");
+        assert_eq!(fields[2].value, &[0x25, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_fields_malformed() {
+        // Truncated length-delimited field.
+        assert!(fields(&[0x12, 0x05, b'h']).any(|f| f.is_err()));
+        // Field number 0.
+        assert!(fields(&[0x00, 0x01]).any(|f| f.is_err()));
+        // Mismatched end group.
+        assert!(fields(&[0x1b, 0x24]).any(|f| f.is_err()));
+        // Stray end group.
+        assert!(fields(&[0x1c]).any(|f| f.is_err()));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&[]), Ok(()));
+        assert_eq!(validate(&[0x08, 0x01, 0x12, 0x01, b'h', 0x1b, 0x08, 0x01, 0x1c]), Ok(()));
+
+        let err = validate(&[0x08, 0x01, 0x12, 0x05, b'h']).unwrap_err();
+        assert_eq!(err.offset(), Some(5));
+        assert!(validate(&[0x1c]).is_err());
+    }
+
+    #[test]
+    fn test_validate_known_fields() {
+        let known: &[(u32, &[WireType])] =
+            &[(1, &[WireType::Varint]), (2, &[WireType::Delimited]), (5, &[WireType::Fixed64])];
+        assert!(validate_known_fields(DATA, known).is_ok());
+        // Unknown fields can have any wire type.
+        assert!(validate_known_fields(DATA, &[]).is_ok());
+
+        let err = validate_known_fields(DATA, &[(2, &[WireType::Varint])]).unwrap_err();
+        assert_eq!((err.offset(), err.field_path()), (Some(3), &[2][..]));
+        let err = validate_known_fields(DATA, &[(3, &[WireType::Delimited])]).unwrap_err();
+        assert_eq!((err.offset(), err.field_path()), (Some(7), &[3][..]));
+        // Structural errors are still reported.
+        assert!(validate_known_fields(&[0x08], known).is_err());
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        // Truncated length-delimited field 2.
+        let err = ParseError::locate(&[0x08, 0x01, 0x12, 0x05, b'h']);
+        assert_eq!((err.offset(), err.field_path()), (Some(5), &[2][..]));
+        // Field number 0.
+        let err = ParseError::locate(&[0x08, 0x01, 0x00, 0x01]);
+        assert_eq!((err.offset(), err.field_path()), (Some(2), &[][..]));
+        // Truncated field 2 inside group 3.
+        let err = ParseError::locate(&[0x1b, 0x08, 0x01, 0x12, 0x05, b'h']);
+        assert_eq!((err.offset(), err.field_path()), (Some(6), &[3, 2][..]));
+        assert_eq!(err.snippet(), [0x1b, 0x08, 0x01, 0x12, 0x05, b'h']);
+        assert_eq!(
+            err.to_string(),
+            "Couldn't deserialize given bytes into a proto at byte 6 in field 3.2 \
+             (near 1b 08 01 12 05 68)"
+        );
+        // Mismatched end group.
+        let err = ParseError::locate(&[0x1b, 0x24]);
+        assert_eq!((err.offset(), err.field_path()), (Some(1), &[3][..]));
+        // Well-formed data has no known location.
+        let err = ParseError::locate(&[0x08, 0x01]);
+        assert_eq!((err.offset(), err.field_path()), (None, &[][..]));
+        assert_eq!(err.snippet(), []);
+        // The snippet is limited to 8 bytes on either side.
+        let mut data = [0x08u8, 0x01].repeat(10);
+        data.push(0x00);
+        data.extend_from_slice(&[0x08, 0x01].repeat(10));
+        let err = ParseError::locate(&data);
+        assert_eq!(err.offset(), Some(20));
+        assert_eq!(err.snippet(), &data[12..28]);
+    }
+
+    #[test]
+    fn test_field_stats() {
+        let mut data = DATA.to_vec();
+        data.extend_from_slice(&[0x12, 0x03, b'a', b'b', b'c', 0x08, 0x01]);
+        assert_eq!(
+            field_stats(&data).unwrap(),
+            [
+                FieldStats { number: 1, count: 2, bytes: 5 },
+                FieldStats { number: 2, count: 2, bytes: 9 },
+                FieldStats { number: 3, count: 1, bytes: 7 },
+                FieldStats { number: 5, count: 1, bytes: 9 },
+            ]
+        );
+        assert_eq!(field_stats(&[]).unwrap(), []);
+        assert!(field_stats(&[0x12, 0x05]).is_err());
+    }
+
+    #[test]
+    fn test_fields_group_depth_limit() {
+        let mut data = vec![0x1b; MAX_DEPTH + 1];
+        data.extend(vec![0x1c; MAX_DEPTH + 1]);
+        assert!(fields(&data).any(|f| f.is_err()));
+
+        let mut data = vec![0x1b; MAX_DEPTH];
+        data.extend(vec![0x1c; MAX_DEPTH]);
+        assert!(fields(&data).all(|f| f.is_ok()));
+    }
+
+    #[test]
+    fn test_field_values() {
+        let scanner = scan(DATA);
+        assert_eq!(scanner.get_field(1).unwrap().unwrap().as_varint(), Some(150));
+        assert_eq!(scanner.get_field(2).unwrap().unwrap().as_str(), Some("hi"));
+        assert_eq!(scanner.get_field(5).unwrap().unwrap().as_fixed64(), Some(1));
+        assert_eq!(scanner.get_field(1).unwrap().unwrap().as_bytes(), None);
+        assert_eq!(scanner.get_field(2).unwrap().unwrap().as_varint(), None);
+        assert_eq!(scanner.get_field(4).unwrap(), None);
+
+        let group = scanner.get_field(3).unwrap().unwrap();
+        assert_eq!(scan(group.value).get_field(4).unwrap().unwrap().as_fixed32(), Some(1));
+    }
+
+    #[test]
+    fn test_fixed_width_values_are_little_endian() {
+        // Values whose bytes all differ, so that decoding in host byte order
+        // would fail on big-endian targets.
+        let data = [
+            0x0d, 0x04, 0x03, 0x02, 0x01, // Field 1, fixed32.
+            0x11, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01, // Field 2, fixed64.
+        ];
+        let scanner = scan(&data);
+        assert_eq!(scanner.get_field(1).unwrap().unwrap().as_fixed32(), Some(0x01020304));
+        assert_eq!(scanner.get_field(2).unwrap().unwrap().as_fixed64(), Some(0x0102030405060708));
+    }
+
+    #[test]
+    fn test_scanner_last_occurrence_wins() {
+        let data = [0x08, 0x01, 0x10, 0x05, 0x08, 0x02];
+        let scanner = scan(&data);
+        assert_eq!(scanner.get_field(1).unwrap().unwrap().as_varint(), Some(2));
+        let all: Vec<_> =
+            scanner.get_repeated(1).unwrap().iter().map(|f| f.as_varint().unwrap()).collect();
+        assert_eq!(all, [1, 2]);
+    }
+
+    #[test]
+    fn test_scanner_malformed() {
+        let data = [0x08, 0x01, 0x12, 0x05];
+        assert!(scan(&data).get_field(1).is_err());
+        assert!(scan(&data).get_repeated(1).is_err());
+    }
+
+    #[test]
+    fn test_retain_fields() {
+        assert_eq!(retain_fields(DATA, &[2, 5]).unwrap(), [&DATA[3..7], &DATA[14..]].concat());
+        assert_eq!(retain_fields(DATA, &[]).unwrap(), []);
+        assert!(retain_fields(&[0x12, 0x05], &[1]).is_err());
+    }
+}
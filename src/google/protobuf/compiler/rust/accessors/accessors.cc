@@ -56,6 +56,12 @@ std::unique_ptr<AccessorGenerator> AccessorGenerator::For(
     case FieldDescriptor::TYPE_BYTES:
       if (field.desc().is_repeated()) return nullptr;
       return ForSingularBytes(field);
+    case FieldDescriptor::TYPE_STRING:
+      // Strings only get raw byte accessors so far, and only on request.
+      if (field.desc().is_repeated() || !field.opts().string_bytes_accessors) {
+        return nullptr;
+      }
+      return ForSingularBytes(field);
 
     default:
       return nullptr;
@@ -29,7 +29,9 @@
 // OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
 
 #include <memory>
+#include <string>
 
+#include "absl/strings/str_cat.h"
 #include "absl/strings/string_view.h"
 #include "google/protobuf/compiler/cpp/helpers.h"
 #include "google/protobuf/compiler/rust/accessors/accessors.h"
@@ -42,14 +44,44 @@ namespace protobuf {
 namespace compiler {
 namespace rust {
 namespace {
+// `string` fields handled here are exposed as raw bytes, under a name that
+// leaves room for UTF-8 checked accessors.
+std::string AccessorName(Context<FieldDescriptor> field) {
+  if (field.desc().type() == FieldDescriptor::TYPE_STRING) {
+    return absl::StrCat(field.desc().name(), "_bytes");
+  }
+  return field.desc().name();
+}
+
 class SingularBytes final : public AccessorGenerator {
  public:
   ~SingularBytes() override = default;
 
   void InMsgImpl(Context<FieldDescriptor> field) const override {
+    if (!field.desc().has_presence()) {
+      field.Emit(
+          {
+              {"field", AccessorName(field)},
+              {"getter_thunk", Thunk(field, "get")},
+              {"setter_thunk", Thunk(field, "set")},
+          },
+          R"rs(
+            pub fn $field$(&self) -> &[u8] {
+              unsafe {
+                let val = $getter_thunk$(self.msg);
+                $std$::slice::from_raw_parts(val.ptr, val.len)
+              }
+            }
+            pub fn $field$_set(&mut self, val: &[u8]) {
+              unsafe { $setter_thunk$(self.msg, val.as_ptr(), val.len()) }
+            }
+          )rs");
+      return;
+    }
+
     field.Emit(
         {
-            {"field", field.desc().name()},
+            {"field", AccessorName(field)},
             {"hazzer_thunk", Thunk(field, "has")},
             {"getter_thunk", Thunk(field, "get")},
             {"setter_thunk", Thunk(field, "set")},
@@ -81,10 +113,17 @@ class SingularBytes final : public AccessorGenerator {
             {"getter_thunk", Thunk(field, "get")},
             {"setter_thunk", Thunk(field, "set")},
             {"clearer_thunk", Thunk(field, "clear")},
+            {"hazzer",
+             [&] {
+               if (!field.desc().has_presence()) return;
+               field.Emit(R"rs(
+                 fn $hazzer_thunk$(raw_msg: $NonNull$<u8>) -> bool;
+               )rs");
+             }},
         },
         R"rs(
-          fn $hazzer_thunk$(raw_msg: $NonNull$<u8>) -> bool;
-          fn $getter_thunk$(raw_msg: $NonNull$<u8>) -> $pb$::PtrAndLen;
+          $hazzer$
+          fn $getter_thunk$(raw_msg: $NonNull$<u8>) -> $pbi$::PtrAndLen;
           fn $setter_thunk$(raw_msg: $NonNull$<u8>, val: *const u8, len: usize);
           fn $clearer_thunk$(raw_msg: $NonNull$<u8>);
         )rs");
@@ -100,11 +139,18 @@ class SingularBytes final : public AccessorGenerator {
             {"getter_thunk", Thunk(field, "get")},
             {"setter_thunk", Thunk(field, "set")},
             {"clearer_thunk", Thunk(field, "clear")},
+            {"hazzer",
+             [&] {
+               if (!field.desc().has_presence()) return;
+               field.Emit(R"cc(
+                 bool $hazzer_thunk$($QualifiedMsg$* msg) {
+                   return msg->has_$field$();
+                 }
+               )cc");
+             }},
         },
         R"cc(
-          bool $hazzer_thunk$($QualifiedMsg$* msg) {
-            return msg->has_$field$();
-          }
+          $hazzer$
           ::google::protobuf::rust_internal::PtrAndLen $getter_thunk$($QualifiedMsg$* msg) {
             absl::string_view val = msg->$field$();
             return google::protobuf::rust_internal::PtrAndLen(val.data(), val.size());
@@ -47,29 +47,10 @@ class SingularScalar final : public AccessorGenerator {
   ~SingularScalar() override = default;
 
   void InMsgImpl(Context<FieldDescriptor> field) const override {
-    field.Emit(
-        {
-            {"field", field.desc().name()},
-            {"Scalar", PrimitiveRsTypeName(field)},
-            {"hazzer_thunk", Thunk(field, "has")},
-            {"getter_thunk", Thunk(field, "get")},
-            {"setter_thunk", Thunk(field, "set")},
-            {"clearer_thunk", Thunk(field, "clear")},
-        },
-        R"rs(
-          pub fn $field$(&self) -> Option<$Scalar$> {
-            if !unsafe { $hazzer_thunk$(self.msg) } {
-              return None;
-            }
-            Some(unsafe { $getter_thunk$(self.msg) })
-          }
-          pub fn $field$_set(&mut self, val: Option<$Scalar$>) {
-            match val {
-              Some(val) => unsafe { $setter_thunk$(self.msg, val) },
-              None => unsafe { $clearer_thunk$(self.msg) },
-            }
-          }
-        )rs");
+    EmitAccessors(field);
+    if (field.desc().type() != FieldDescriptor::TYPE_BOOL) {
+      EmitCheckedSetter(field);
+    }
   }
 
   void InExternC(Context<FieldDescriptor> field) const override {
@@ -80,9 +61,16 @@ class SingularScalar final : public AccessorGenerator {
             {"getter_thunk", Thunk(field, "get")},
             {"setter_thunk", Thunk(field, "set")},
             {"clearer_thunk", Thunk(field, "clear")},
+            {"hazzer",
+             [&] {
+               if (!field.desc().has_presence()) return;
+               field.Emit(R"rs(
+                 fn $hazzer_thunk$(raw_msg: $NonNull$<u8>) -> bool;
+               )rs");
+             }},
         },
         R"rs(
-          fn $hazzer_thunk$(raw_msg: $NonNull$<u8>) -> bool;
+          $hazzer$
           fn $getter_thunk$(raw_msg: $NonNull$<u8>) -> $Scalar$;
           fn $setter_thunk$(raw_msg: $NonNull$<u8>, val: $Scalar$);
           fn $clearer_thunk$(raw_msg: $NonNull$<u8>);
@@ -100,11 +88,18 @@ class SingularScalar final : public AccessorGenerator {
             {"getter_thunk", Thunk(field, "get")},
             {"setter_thunk", Thunk(field, "set")},
             {"clearer_thunk", Thunk(field, "clear")},
+            {"hazzer",
+             [&] {
+               if (!field.desc().has_presence()) return;
+               field.Emit(R"cc(
+                 bool $hazzer_thunk$($QualifiedMsg$* msg) {
+                   return msg->has_$field$();
+                 }
+               )cc");
+             }},
         },
         R"cc(
-          bool $hazzer_thunk$($QualifiedMsg$* msg) {
-            return msg->has_$field$();
-          }
+          $hazzer$
           $Scalar$ $getter_thunk$($QualifiedMsg$* msg) { return msg->$field$(); }
           void $setter_thunk$($QualifiedMsg$* msg, $Scalar$ val) {
             msg->set_$field$(val);
@@ -112,6 +107,74 @@ class SingularScalar final : public AccessorGenerator {
           void $clearer_thunk$($QualifiedMsg$* msg) { msg->clear_$field$(); }
         )cc");
   }
+ private:
+  void EmitAccessors(Context<FieldDescriptor> field) const {
+    if (!field.desc().has_presence()) {
+      // Fields with implicit presence can't be unset; reading them always
+      // yields a value (the default if nothing was written).
+      field.Emit(
+          {
+              {"field", field.desc().name()},
+              {"Scalar", PrimitiveRsTypeName(field)},
+              {"getter_thunk", Thunk(field, "get")},
+              {"setter_thunk", Thunk(field, "set")},
+          },
+          R"rs(
+            pub fn $field$(&self) -> $Scalar$ {
+              unsafe { $getter_thunk$(self.msg) }
+            }
+            pub fn $field$_set(&mut self, val: $Scalar$) {
+              unsafe { $setter_thunk$(self.msg, val) }
+            }
+          )rs");
+      return;
+    }
+
+    field.Emit(
+        {
+            {"field", field.desc().name()},
+            {"Scalar", PrimitiveRsTypeName(field)},
+            {"hazzer_thunk", Thunk(field, "has")},
+            {"getter_thunk", Thunk(field, "get")},
+            {"setter_thunk", Thunk(field, "set")},
+            {"clearer_thunk", Thunk(field, "clear")},
+        },
+        R"rs(
+          pub fn $field$(&self) -> Option<$Scalar$> {
+            if !unsafe { $hazzer_thunk$(self.msg) } {
+              return None;
+            }
+            Some(unsafe { $getter_thunk$(self.msg) })
+          }
+          pub fn $field$_set(&mut self, val: Option<$Scalar$>) {
+            match val {
+              Some(val) => unsafe { $setter_thunk$(self.msg, val) },
+              None => unsafe { $clearer_thunk$(self.msg) },
+            }
+          }
+        )rs");
+  }
+
+  // Emits a setter taking any integer type, which fails instead of truncating
+  // values that don't fit the field.
+  void EmitCheckedSetter(Context<FieldDescriptor> field) const {
+    field.Emit(
+        {
+            {"field", field.desc().name()},
+            {"Scalar", PrimitiveRsTypeName(field)},
+            {"val", field.desc().has_presence() ? "Some(val)" : "val"},
+        },
+        R"rs(
+          pub fn $field$_try_set<T>(&mut self, val: T) -> Result<(), T::Error>
+          where
+            T: $std$::convert::TryInto<$Scalar$>,
+          {
+            let val = val.try_into()?;
+            self.$field$_set($val$);
+            Ok(())
+          }
+        )rs");
+  }
 };
 }  // namespace
 
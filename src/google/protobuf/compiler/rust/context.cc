@@ -37,6 +37,7 @@
 #include "absl/algorithm/container.h"
 #include "absl/status/status.h"
 #include "absl/status/statusor.h"
+#include "absl/strings/str_split.h"
 #include "absl/strings/string_view.h"
 #include "absl/strings/substitute.h"
 #include "google/protobuf/compiler/code_generator.h"
@@ -87,6 +88,50 @@ absl::StatusOr<Options> Options::Parse(absl::string_view param) {
                          kernel_arg->second));
   }
 
+  for (const auto& arg : args) {
+    if (arg.first == "string_bytes_accessors") {
+      if (arg.second != "true" && arg.second != "false") {
+        return absl::InvalidArgumentError(absl::Substitute(
+            "Invalid value `$0` for `string_bytes_accessors`, please specify "
+            "`true` or `false`.",
+            arg.second));
+      }
+      opts.string_bytes_accessors = arg.second == "true";
+      continue;
+    }
+
+    if (arg.first == "constructor_fields") {
+      for (absl::string_view entry :
+           absl::StrSplit(arg.second, '+', absl::SkipEmpty())) {
+        std::vector<std::string> parts = absl::StrSplit(entry, ':');
+        std::string name = std::move(parts.front());
+        parts.erase(parts.begin());
+        absl::flat_hash_set<absl::string_view> seen;
+        bool valid = !name.empty() && !parts.empty() &&
+                     !opts.constructor_fields.contains(name) &&
+                     absl::c_all_of(parts, [&](absl::string_view field) {
+                       return !field.empty() && seen.insert(field).second;
+                     });
+        if (!valid) {
+          return absl::InvalidArgumentError(absl::Substitute(
+              "Invalid entry `$0` for `constructor_fields`, please specify "
+              "`package.Message:field1:field2` with distinct fields, once "
+              "per message.",
+              entry));
+        }
+        opts.constructor_fields[name] = std::move(parts);
+      }
+      continue;
+    }
+
+    if (arg.first == "doc_hidden_messages") {
+      for (absl::string_view name :
+           absl::StrSplit(arg.second, '+', absl::SkipEmpty())) {
+        opts.doc_hidden_messages.emplace(name);
+      }
+    }
+  }
+
   return opts;
 }
 
@@ -31,6 +31,11 @@
 #ifndef GOOGLE_PROTOBUF_COMPILER_RUST_CONTEXT_H__
 #define GOOGLE_PROTOBUF_COMPILER_RUST_CONTEXT_H__
 
+#include <string>
+#include <vector>
+
+#include "absl/container/flat_hash_map.h"
+#include "absl/container/flat_hash_set.h"
 #include "absl/log/absl_log.h"
 #include "absl/status/statusor.h"
 #include "absl/strings/string_view.h"
@@ -63,6 +68,22 @@ inline absl::string_view KernelRsName(Kernel kernel) {
 struct Options {
   Kernel kernel;
 
+  // Fully qualified names of messages whose generated types should be
+  // `#[doc(hidden)]`, set via `doc_hidden_messages=a.B+a.C`.
+  absl::flat_hash_set<std::string> doc_hidden_messages;
+
+  // Fields taken as arguments by a generated `create()` constructor, keyed by
+  // the fully qualified message name. Set via
+  // `constructor_fields=a.B:x:y+a.C:z`, which generates `B::create(x, y)` and
+  // `C::create(z)`.
+  absl::flat_hash_map<std::string, std::vector<std::string>>
+      constructor_fields;
+
+  // Whether to generate `field_bytes()`/`field_bytes_set()` accessors that
+  // read and write `string` fields as raw bytes, without UTF-8 checks. Set via
+  // `string_bytes_accessors=true`.
+  bool string_bytes_accessors = false;
+
   static absl::StatusOr<Options> Parse(absl::string_view param);
 };
 
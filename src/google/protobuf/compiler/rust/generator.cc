@@ -36,8 +36,10 @@
 
 #include "absl/algorithm/container.h"
 #include "absl/memory/memory.h"
+#include "absl/strings/str_cat.h"
 #include "absl/strings/str_split.h"
 #include "absl/strings/string_view.h"
+#include "absl/strings/substitute.h"
 #include "google/protobuf/compiler/code_generator.h"
 #include "google/protobuf/compiler/cpp/names.h"
 #include "google/protobuf/compiler/rust/context.h"
@@ -53,6 +55,10 @@ namespace protobuf {
 namespace compiler {
 namespace rust {
 namespace {
+// The version of the interface between generated code and the runtime. Must
+// match `GENCODE_VERSION` in rust/runtime_api.rs.
+constexpr int kGencodeVersion = 2;
+
 void EmitOpeningOfPackageModules(Context<FileDescriptor> file) {
   if (file.desc().package().empty()) return;
   for (absl::string_view segment : absl::StrSplit(file.desc().package(), '.')) {
@@ -75,6 +81,33 @@ void EmitClosingOfPackageModules(Context<FileDescriptor> file) {
     )rs");
   }
 }
+
+// Checks that the `string_bytes_accessors` accessors of the fields of `msg`
+// and its nested messages don't share a name with the accessors of another
+// field, e.g. `name_bytes()` for `name` and for a field `name_bytes`.
+bool CheckStringBytesAccessors(const Descriptor& msg, std::string* error) {
+  for (int i = 0; i < msg.field_count(); ++i) {
+    const FieldDescriptor& field = *msg.field(i);
+    if (field.type() != FieldDescriptor::TYPE_STRING || field.is_repeated() ||
+        field.options().has_ctype()) {
+      continue;
+    }
+    for (absl::string_view suffix : {"_bytes", "_bytes_set"}) {
+      std::string accessor = absl::StrCat(field.name(), suffix);
+      if (msg.FindFieldByName(accessor) != nullptr) {
+        *error = absl::Substitute(
+            "string_bytes_accessors: accessor `$0` of `$1` collides with the "
+            "field `$0`.",
+            accessor, field.full_name());
+        return false;
+      }
+    }
+  }
+  for (int i = 0; i < msg.nested_type_count(); ++i) {
+    if (!CheckStringBytesAccessors(*msg.nested_type(i), error)) return false;
+  }
+  return true;
+}
 }  // namespace
 
 bool RustGenerator::Generate(const FileDescriptor* file_desc,
@@ -89,6 +122,39 @@ bool RustGenerator::Generate(const FileDescriptor* file_desc,
 
   Context<FileDescriptor> file(&*opts, file_desc, nullptr);
 
+  // Every message named in `constructor_fields` must be in one of the files
+  // being generated, so that typos in message names don't go unnoticed.
+  std::vector<const FileDescriptor*> files_to_generate;
+  generator_context->ListParsedFiles(&files_to_generate);
+  for (const auto& [name, fields] : file.opts().constructor_fields) {
+    const Descriptor* msg = file_desc->pool()->FindMessageTypeByName(name);
+    if (msg == nullptr ||
+        !absl::c_linear_search(files_to_generate, msg->file())) {
+      *error =
+          absl::Substitute("constructor_fields: unknown message `$0`.", name);
+      return false;
+    }
+    if (msg->file() != file_desc) continue;
+    for (const auto& field_name : fields) {
+      const FieldDescriptor* field = msg->FindFieldByName(field_name);
+      if (field == nullptr || !IsSupportedFieldType(file.WithDesc(field))) {
+        *error = absl::Substitute(
+            "constructor_fields: `$0` has no field `$1` with generated "
+            "accessors.",
+            name, field_name);
+        return false;
+      }
+    }
+  }
+
+  if (file.opts().string_bytes_accessors) {
+    for (int i = 0; i < file_desc->message_type_count(); ++i) {
+      if (!CheckStringBytesAccessors(*file_desc->message_type(i), error)) {
+        return false;
+      }
+    }
+  }
+
   auto outfile = absl::WrapUnique(generator_context->Open(GetRsFile(file)));
   io::Printer printer(outfile.get());
   file = file.WithPrinter(&printer);
@@ -97,14 +163,18 @@ bool RustGenerator::Generate(const FileDescriptor* file_desc,
   auto v = file.printer().WithVars({
       {"std", "::__std"},
       {"pb", "::__pb"},
-      {"pbi", "::__pb::__runtime"},
+      {"pbi", "::__pb::runtime_api"},
       {"NonNull", "::__std::ptr::NonNull"},
   });
 
-  file.Emit({{"kernel", KernelRsName(file.opts().kernel)}}, R"rs(
+  file.Emit({{"kernel", KernelRsName(file.opts().kernel)},
+             {"version", kGencodeVersion}},
+            R"rs(
     extern crate protobuf_$kernel$ as __pb;
     extern crate std as __std;
 
+    const _: () = ::__pb::runtime_api::check_gencode_version($version$);
+
   )rs");
   EmitOpeningOfPackageModules(file);
 
@@ -0,0 +1,214 @@
+// Protocol Buffers - Google's data interchange format
+// Copyright 2023 Google LLC.  All rights reserved.
+// https://developers.google.com/protocol-buffers/
+//
+// Redistribution and use in source and binary forms, with or without
+// modification, are permitted provided that the following conditions are
+// met:
+//
+//     * Redistributions of source code must retain the above copyright
+// notice, this list of conditions and the following disclaimer.
+//     * Redistributions in binary form must reproduce the above
+// copyright notice, this list of conditions and the following disclaimer
+// in the documentation and/or other materials provided with the
+// distribution.
+//     * Neither the name of Google LLC. nor the names of its
+// contributors may be used to endorse or promote products derived from
+// this software without specific prior written permission.
+//
+// THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS
+// "AS IS" AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT
+// LIMITED TO, THE IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR
+// A PARTICULAR PURPOSE ARE DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT
+// OWNER OR CONTRIBUTORS BE LIABLE FOR ANY DIRECT, INDIRECT, INCIDENTAL,
+// SPECIAL, EXEMPLARY, OR CONSEQUENTIAL DAMAGES (INCLUDING, BUT NOT
+// LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR SERVICES; LOSS OF USE,
+// DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER CAUSED AND ON ANY
+// THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY, OR TORT
+// (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+// OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+
+#include <string>
+#include <vector>
+
+#include "google/protobuf/testing/file.h"
+#include "google/protobuf/compiler/command_line_interface.h"
+#include "google/protobuf/compiler/rust/context.h"
+#include "google/protobuf/compiler/rust/generator.h"
+#include "google/protobuf/testing/googletest.h"
+#include <gtest/gtest.h>
+#include "absl/log/absl_check.h"
+#include "absl/strings/ascii.h"
+#include "absl/strings/match.h"
+#include "absl/strings/str_cat.h"
+#include "absl/strings/string_view.h"
+
+namespace google {
+namespace protobuf {
+namespace compiler {
+namespace rust {
+namespace {
+
+constexpr absl::string_view kTestProto = R"proto(
+  syntax = "proto2";
+  package pkg;
+  message Foo {
+    optional int64 x = 1;
+    optional string name = 2;
+    optional int64 type = 3;
+  }
+  message Bar {
+    optional int32 y = 1;
+  }
+)proto";
+
+// Runs the generator with the upb kernel and `params` on `proto`, returning
+// the generated Rust code, or the empty string if generation failed.
+std::string Generate(absl::string_view params,
+                     absl::string_view proto = kTestProto) {
+  ABSL_CHECK_OK(File::SetContents(absl::StrCat(TestTempDir(), "/test.proto"),
+                                  proto, true));
+
+  CommandLineInterface cli;
+  cli.SetInputsAreProtoPathRelative(true);
+  RustGenerator rust_generator;
+  cli.RegisterGenerator("--rust_out", &rust_generator, "");
+  std::string proto_path = absl::StrCat("-I", TestTempDir());
+  std::string rust_out =
+      absl::StrCat("--rust_out=experimental-codegen=enabled,kernel=upb",
+                   params.empty() ? "" : ",", params, ":", TestTempDir());
+  const char* argv[] = {"protoc", proto_path.c_str(), rust_out.c_str(),
+                        "test.proto"};
+  if (cli.Run(4, argv) != 0) return "";
+
+  std::string output;
+  ABSL_CHECK_OK(File::GetContents(absl::StrCat(TestTempDir(), "/test.u.pb.rs"),
+                                  &output, true));
+  return output;
+}
+
+// Returns whether the first occurrence of `item` in `output` is preceded by
+// `#[doc(hidden)]`.
+bool HasDocHidden(absl::string_view output, absl::string_view item) {
+  size_t pos = output.find(item);
+  ABSL_CHECK_NE(pos, absl::string_view::npos) << item;
+  absl::string_view before =
+      absl::StripTrailingAsciiWhitespace(output.substr(0, pos));
+  return absl::EndsWith(before, "#[doc(hidden)]");
+}
+
+TEST(RustOptionsTest, ParsesDocHiddenMessages) {
+  auto opts = Options::Parse(
+      "experimental-codegen=enabled,kernel=upb,doc_hidden_messages=a.B+a.C");
+  ASSERT_TRUE(opts.ok()) << opts.status();
+  EXPECT_EQ(opts->doc_hidden_messages.size(), 2);
+  EXPECT_TRUE(opts->doc_hidden_messages.contains("a.B"));
+  EXPECT_TRUE(opts->doc_hidden_messages.contains("a.C"));
+}
+
+TEST(RustOptionsTest, ParsesConstructorFields) {
+  auto opts = Options::Parse(
+      "experimental-codegen=enabled,kernel=upb,"
+      "constructor_fields=a.B:x:y+a.C:z");
+  ASSERT_TRUE(opts.ok()) << opts.status();
+  EXPECT_EQ(opts->constructor_fields.size(), 2);
+  EXPECT_EQ(opts->constructor_fields["a.B"],
+            (std::vector<std::string>{"x", "y"}));
+  EXPECT_EQ(opts->constructor_fields["a.C"], std::vector<std::string>{"z"});
+}
+
+TEST(RustOptionsTest, RejectsInvalidConstructorFields) {
+  for (absl::string_view value :
+       {"a.B", "a.B:", ":x", "a.B:x::y", "a.B:x:x", "a.B:x+a.B:y"}) {
+    auto opts = Options::Parse(absl::StrCat(
+        "experimental-codegen=enabled,kernel=upb,constructor_fields=", value));
+    EXPECT_FALSE(opts.ok()) << value;
+  }
+}
+
+TEST(RustGeneratorTest, ConstructorFields) {
+  std::string output = Generate("");
+  ASSERT_FALSE(output.empty());
+  EXPECT_FALSE(absl::StrContains(output, "pub fn create("));
+
+  // Parameters named after Rust keywords are escaped.
+  output = Generate("constructor_fields=pkg.Foo:type:x");
+  ASSERT_FALSE(output.empty());
+  EXPECT_TRUE(absl::StrContains(output,
+                                "pub fn create(r#type: i64, x: i64, ) -> Self"));
+  EXPECT_TRUE(absl::StrContains(output, "__msg.type_set(Some(r#type));"));
+  EXPECT_TRUE(absl::StrContains(output, "__msg.x_set(Some(x));"));
+}
+
+TEST(RustGeneratorTest, ConstructorFieldsRejectsUnsupportedFields) {
+  EXPECT_TRUE(Generate("constructor_fields=pkg.Fo:x").empty());
+  EXPECT_TRUE(Generate("constructor_fields=pkg.Foo:missing").empty());
+  // `string` fields have no accessors by default.
+  EXPECT_TRUE(Generate("constructor_fields=pkg.Foo:name").empty());
+}
+
+TEST(RustOptionsTest, ParsesStringBytesAccessors) {
+  auto opts = Options::Parse("experimental-codegen=enabled,kernel=upb");
+  ASSERT_TRUE(opts.ok()) << opts.status();
+  EXPECT_FALSE(opts->string_bytes_accessors);
+
+  opts = Options::Parse(
+      "experimental-codegen=enabled,kernel=upb,string_bytes_accessors=true");
+  ASSERT_TRUE(opts.ok()) << opts.status();
+  EXPECT_TRUE(opts->string_bytes_accessors);
+
+  EXPECT_FALSE(Options::Parse("experimental-codegen=enabled,kernel=upb,"
+                              "string_bytes_accessors=yes")
+                   .ok());
+}
+
+TEST(RustGeneratorTest, StringBytesAccessors) {
+  std::string output = Generate("");
+  ASSERT_FALSE(output.empty());
+  EXPECT_FALSE(absl::StrContains(output, "name_bytes"));
+  EXPECT_FALSE(absl::StrContains(output, "pub fn name("));
+  EXPECT_FALSE(absl::StrContains(output, "name: \"name\""));
+
+  output = Generate("string_bytes_accessors=true");
+  ASSERT_FALSE(output.empty());
+  EXPECT_TRUE(absl::StrContains(
+      output, "pub fn name_bytes(&self) -> Option<&[u8]> {"));
+  EXPECT_TRUE(absl::StrContains(
+      output, "pub fn name_bytes_set(&mut self, val: Option<&[u8]>) {"));
+  EXPECT_FALSE(absl::StrContains(output, "pub fn name("));
+  // The field is listed in `FIELDS`, read through its `_bytes` accessor.
+  EXPECT_TRUE(absl::StrContains(output, "name: \"name\""));
+  EXPECT_TRUE(absl::StrContains(output, "|m| m.name_bytes().map("));
+}
+
+TEST(RustGeneratorTest, StringBytesAccessorsRejectCollisions) {
+  constexpr absl::string_view kProto = R"proto(
+    syntax = "proto2";
+    package pkg;
+    message Foo {
+      message Nested {
+        optional string name = 1;
+        optional bytes name_bytes = 2;
+      }
+    }
+  )proto";
+  EXPECT_FALSE(Generate("", kProto).empty());
+  EXPECT_TRUE(Generate("string_bytes_accessors=true", kProto).empty());
+}
+
+TEST(RustGeneratorTest, DocHiddenMessages) {
+  std::string output = Generate("");
+  ASSERT_FALSE(output.empty());
+  EXPECT_FALSE(absl::StrContains(output, "#[doc(hidden)]"));
+
+  output = Generate("doc_hidden_messages=pkg.Foo");
+  ASSERT_FALSE(output.empty());
+  EXPECT_TRUE(HasDocHidden(output, "pub struct Foo {"));
+  EXPECT_FALSE(HasDocHidden(output, "pub struct Bar {"));
+}
+
+}  // namespace
+}  // namespace rust
+}  // namespace compiler
+}  // namespace protobuf
+}  // namespace google
@@ -30,8 +30,11 @@
 
 #include "google/protobuf/compiler/rust/message.h"
 
+#include <vector>
+
 #include "absl/log/absl_check.h"
 #include "absl/log/absl_log.h"
+#include "absl/strings/str_cat.h"
 #include "absl/strings/string_view.h"
 #include "google/protobuf/compiler/cpp/helpers.h"
 #include "google/protobuf/compiler/cpp/names.h"
@@ -89,11 +92,20 @@ void MessageNew(Context<Descriptor> msg) {
   ABSL_LOG(FATAL) << "unreachable";
 }
 
-void MessageSerialize(Context<Descriptor> msg) {
+// With `partial`, the message may have unset required fields. upb never checks
+// them, so this only matters for the C++ kernel.
+void MessageSerialize(Context<Descriptor> msg, bool partial) {
   switch (msg.opts().kernel) {
     case Kernel::kCpp:
-      msg.Emit({{"serialize_thunk", Thunk(msg, "serialize")}}, R"rs(
-        unsafe { $serialize_thunk$(self.msg) }
+      msg.Emit({{"serialize_thunk",
+                 Thunk(msg, partial ? "serialize_partial" : "serialize")}},
+               R"rs(
+        let mut data = $std$::mem::MaybeUninit::<$pb$::SerializedData>::uninit();
+        if unsafe { $serialize_thunk$(self.msg, data.as_mut_ptr()) } {
+          Ok(unsafe { data.assume_init() })
+        } else {
+          Err($pbi$::size_limit_exceeded())
+        }
       )rs");
       return;
 
@@ -103,7 +115,69 @@ void MessageSerialize(Context<Descriptor> msg) {
         let mut len = 0;
         unsafe {
           let data = $serialize_thunk$(self.msg, arena.raw(), &mut len);
-          $pb$::SerializedData::from_raw_parts(arena, data, len)
+          Ok($pb$::SerializedData::from_raw_parts(arena, data, len))
+        }
+      )rs");
+      return;
+  }
+
+  ABSL_LOG(FATAL) << "unreachable";
+}
+
+void MessageSerializeDeterministic(Context<Descriptor> msg) {
+  switch (msg.opts().kernel) {
+    case Kernel::kCpp:
+      msg.Emit({{"serialize_deterministic_thunk",
+                 Thunk(msg, "serialize_deterministic")}},
+               R"rs(
+        let mut data = $std$::mem::MaybeUninit::<$pb$::SerializedData>::uninit();
+        if unsafe { $serialize_deterministic_thunk$(self.msg, data.as_mut_ptr()) } {
+          Ok(unsafe { data.assume_init() })
+        } else {
+          Err($pbi$::size_limit_exceeded())
+        }
+      )rs");
+      return;
+
+    case Kernel::kUpb:
+      msg.Emit({{"serialize_ex_thunk", Thunk(msg, "serialize_ex")}}, R"rs(
+        let arena = $pbi$::Arena::new();
+        let mut len = 0;
+        unsafe {
+          let data = $serialize_ex_thunk$(
+            self.msg,
+            $pbi$::ENCODE_OPTION_DETERMINISTIC,
+            arena.raw(),
+            &mut len,
+          );
+          Ok($pb$::SerializedData::from_raw_parts(arena, data, len))
+        }
+      )rs");
+      return;
+  }
+
+  ABSL_LOG(FATAL) << "unreachable";
+}
+
+void MessageSerializeChunked(Context<Descriptor> msg) {
+  switch (msg.opts().kernel) {
+    case Kernel::kCpp:
+      msg.Emit({{"serialize_chunked_thunk", Thunk(msg, "serialize_chunked")}},
+               R"rs(
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        let ok = $pbi$::with_chunk_sink(&mut sink, |sink| unsafe {
+          $serialize_chunked_thunk$(self.msg, chunk_size, sink)
+        });
+        $pbi$::expect_serialized(ok.then_some(()).ok_or_else($pbi$::size_limit_exceeded));
+      )rs");
+      return;
+
+    case Kernel::kUpb:
+      // upb can only encode into a single buffer, so chunk it afterwards.
+      msg.Emit(R"rs(
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        for chunk in $pbi$::expect_serialized(self.serialize_uninstrumented()).chunks(chunk_size) {
+          sink(chunk);
         }
       )rs");
       return;
@@ -112,12 +186,47 @@ void MessageSerialize(Context<Descriptor> msg) {
   ABSL_LOG(FATAL) << "unreachable";
 }
 
-void MessageDeserialize(Context<Descriptor> msg) {
+void MessageSerializeAppend(Context<Descriptor> msg) {
   switch (msg.opts().kernel) {
     case Kernel::kCpp:
       msg.Emit(
           {
-              {"deserialize_thunk", Thunk(msg, "deserialize")},
+              {"serialized_size_thunk", Thunk(msg, "serialized_size")},
+              {"serialize_to_array_thunk", Thunk(msg, "serialize_to_array")},
+          },
+          R"rs(
+          let len = unsafe { $serialized_size_thunk$(self.msg) };
+          if len > $pbi$::MAX_MESSAGE_SIZE {
+            $pbi$::expect_serialized(Err($pbi$::size_limit_exceeded()))
+          }
+          out.reserve(len);
+          unsafe {
+            let start = out.len();
+            $serialize_to_array_thunk$(self.msg, out.as_mut_ptr().add(start), len);
+            out.set_len(start + len);
+          }
+        )rs");
+      return;
+
+    case Kernel::kUpb:
+      // upb always encodes into an arena, so copy the result over.
+      msg.Emit(R"rs(
+        out.extend_from_slice(&$pbi$::expect_serialized(self.serialize_uninstrumented()));
+      )rs");
+      return;
+  }
+
+  ABSL_LOG(FATAL) << "unreachable";
+}
+
+// See `MessageSerialize()` for `partial`.
+void MessageDeserialize(Context<Descriptor> msg, bool partial) {
+  switch (msg.opts().kernel) {
+    case Kernel::kCpp:
+      msg.Emit(
+          {
+              {"deserialize_thunk",
+               Thunk(msg, partial ? "deserialize_partial" : "deserialize")},
           },
           R"rs(
           let success = unsafe {
@@ -128,7 +237,7 @@ void MessageDeserialize(Context<Descriptor> msg) {
 
             $deserialize_thunk$(self.msg, data)
           };
-          success.then_some(()).ok_or($pb$::ParseError)
+          success.then_some(()).ok_or_else(|| $pbi$::locate_parse_error(data))
         )rs");
       return;
 
@@ -142,7 +251,7 @@ void MessageDeserialize(Context<Descriptor> msg) {
         };
 
         match msg {
-          None => Err($pb$::ParseError),
+          None => Err($pbi$::locate_parse_error(data)),
           Some(msg) => {
             // This assignment causes self.arena to be dropped and to deallocate
             // any previous message pointed/owned to by self.msg.
@@ -158,6 +267,26 @@ void MessageDeserialize(Context<Descriptor> msg) {
   ABSL_LOG(FATAL) << "unreachable";
 }
 
+void MessageSpaceUsed(Context<Descriptor> msg) {
+  switch (msg.opts().kernel) {
+    case Kernel::kCpp:
+      msg.Emit({{"space_used_thunk", Thunk(msg, "space_used")}}, R"rs(
+        unsafe { $space_used_thunk$(self.msg) }
+      )rs");
+      return;
+
+    case Kernel::kUpb:
+      // The message owns its arena exclusively, so everything the arena has
+      // allocated is attributable to this message.
+      msg.Emit(R"rs(
+        self.arena.space_allocated()
+      )rs");
+      return;
+  }
+
+  ABSL_LOG(FATAL) << "unreachable";
+}
+
 void MessageExterns(Context<Descriptor> msg) {
   switch (msg.opts().kernel) {
     case Kernel::kCpp:
@@ -167,12 +296,27 @@ void MessageExterns(Context<Descriptor> msg) {
               {"delete_thunk", Thunk(msg, "delete")},
               {"serialize_thunk", Thunk(msg, "serialize")},
               {"deserialize_thunk", Thunk(msg, "deserialize")},
+              {"serialize_partial_thunk", Thunk(msg, "serialize_partial")},
+              {"deserialize_partial_thunk", Thunk(msg, "deserialize_partial")},
+              {"space_used_thunk", Thunk(msg, "space_used")},
+              {"serialize_chunked_thunk", Thunk(msg, "serialize_chunked")},
+              {"serialized_size_thunk", Thunk(msg, "serialized_size")},
+              {"serialize_to_array_thunk", Thunk(msg, "serialize_to_array")},
+              {"serialize_deterministic_thunk",
+               Thunk(msg, "serialize_deterministic")},
           },
           R"rs(
           fn $new_thunk$() -> $NonNull$<u8>;
           fn $delete_thunk$(raw_msg: $NonNull$<u8>);
-          fn $serialize_thunk$(raw_msg: $NonNull$<u8>) -> $pb$::SerializedData;
+          fn $serialize_thunk$(raw_msg: $NonNull$<u8>, out: *mut $pb$::SerializedData) -> bool;
           fn $deserialize_thunk$(raw_msg: $NonNull$<u8>, data: $pb$::SerializedData) -> bool;
+          fn $serialize_partial_thunk$(raw_msg: $NonNull$<u8>, out: *mut $pb$::SerializedData) -> bool;
+          fn $deserialize_partial_thunk$(raw_msg: $NonNull$<u8>, data: $pb$::SerializedData) -> bool;
+          fn $space_used_thunk$(raw_msg: $NonNull$<u8>) -> usize;
+          fn $serialize_chunked_thunk$(raw_msg: $NonNull$<u8>, chunk_size: usize, sink: $pbi$::ChunkSink) -> bool;
+          fn $serialized_size_thunk$(raw_msg: $NonNull$<u8>) -> usize;
+          fn $serialize_to_array_thunk$(raw_msg: $NonNull$<u8>, data: *mut u8, len: usize);
+          fn $serialize_deterministic_thunk$(raw_msg: $NonNull$<u8>, out: *mut $pb$::SerializedData) -> bool;
         )rs");
       return;
 
@@ -181,11 +325,13 @@ void MessageExterns(Context<Descriptor> msg) {
           {
               {"new_thunk", Thunk(msg, "new")},
               {"serialize_thunk", Thunk(msg, "serialize")},
+              {"serialize_ex_thunk", Thunk(msg, "serialize_ex")},
               {"deserialize_thunk", Thunk(msg, "parse")},
           },
           R"rs(
           fn $new_thunk$(arena: $pbi$::RawArena) -> $NonNull$<u8>;
           fn $serialize_thunk$(msg: $NonNull$<u8>, arena: $pbi$::RawArena, len: &mut usize) -> $NonNull$<u8>;
+          fn $serialize_ex_thunk$(msg: $NonNull$<u8>, options: i32, arena: $pbi$::RawArena, len: &mut usize) -> $NonNull$<u8>;
           fn $deserialize_thunk$(data: *const u8, size: usize, arena: $pbi$::RawArena) -> *mut u8;
       )rs");
       return;
@@ -205,6 +351,182 @@ void MessageDrop(Context<Descriptor> msg) {
     unsafe { $delete_thunk$(self.msg); }
   )rs");
 }
+
+void MessageFieldTable(Context<Descriptor> msg) {
+  for (int i = 0; i < msg.desc().field_count(); ++i) {
+    auto field = msg.WithDesc(msg.desc().field(i));
+    // `string` fields only have accessors with `string_bytes_accessors`, which
+    // read them as bytes under the name `<field>_bytes`.
+    bool string_bytes = field.desc().type() == FieldDescriptor::TYPE_STRING &&
+                        AccessorGenerator::For(field) != nullptr;
+    if (!IsSupportedFieldType(field) && !string_bytes) continue;
+
+    absl::string_view variant;
+    absl::string_view is_set;
+    switch (field.desc().type()) {
+      case FieldDescriptor::TYPE_INT64:
+        variant = "Int64";
+        is_set = "|v| *v != 0";
+        break;
+      case FieldDescriptor::TYPE_BOOL:
+        variant = "Bool";
+        is_set = "|v| *v";
+        break;
+      case FieldDescriptor::TYPE_BYTES:
+      case FieldDescriptor::TYPE_STRING:
+        variant = "Bytes";
+        is_set = "|v| !v.is_empty()";
+        break;
+      default:
+        ABSL_LOG(FATAL) << "unreachable";
+    }
+
+    field.Emit(
+        {
+            {"number", field.desc().number()},
+            {"field", field.desc().name()},
+            {"accessor", string_bytes
+                             ? absl::StrCat(field.desc().name(), "_bytes")
+                             : field.desc().name()},
+            {"Variant", variant},
+            {"get",
+             [&] {
+               if (field.desc().has_presence()) {
+                 field.Emit(R"rs(
+                   |m| m.$accessor$().map($pb$::FieldValue::$Variant$)
+                 )rs");
+               } else {
+                 field.Emit({{"is_set", is_set}}, R"rs(
+                   |m| Some(m.$accessor$()).filter($is_set$).map($pb$::FieldValue::$Variant$)
+                 )rs");
+               }
+             }},
+        },
+        R"rs(
+          $pb$::FieldInfo {
+            number: $number$,
+            name: "$field$",
+            get: $get$,
+          },
+        )rs");
+  }
+}
+
+// Emits the wire types `field` can be encoded with, as a Rust slice.
+void FieldWireTypes(Context<FieldDescriptor> field) {
+  absl::string_view wire_type;
+  switch (field.desc().type()) {
+    case FieldDescriptor::TYPE_INT32:
+    case FieldDescriptor::TYPE_INT64:
+    case FieldDescriptor::TYPE_UINT32:
+    case FieldDescriptor::TYPE_UINT64:
+    case FieldDescriptor::TYPE_SINT32:
+    case FieldDescriptor::TYPE_SINT64:
+    case FieldDescriptor::TYPE_BOOL:
+    case FieldDescriptor::TYPE_ENUM:
+      wire_type = "Varint";
+      break;
+    case FieldDescriptor::TYPE_FIXED64:
+    case FieldDescriptor::TYPE_SFIXED64:
+    case FieldDescriptor::TYPE_DOUBLE:
+      wire_type = "Fixed64";
+      break;
+    case FieldDescriptor::TYPE_FIXED32:
+    case FieldDescriptor::TYPE_SFIXED32:
+    case FieldDescriptor::TYPE_FLOAT:
+      wire_type = "Fixed32";
+      break;
+    case FieldDescriptor::TYPE_STRING:
+    case FieldDescriptor::TYPE_BYTES:
+    case FieldDescriptor::TYPE_MESSAGE:
+      wire_type = "Delimited";
+      break;
+    case FieldDescriptor::TYPE_GROUP:
+      wire_type = "StartGroup";
+      break;
+    default:
+      ABSL_LOG(FATAL) << "unreachable";
+  }
+
+  // Parsers accept repeated scalars both packed and unpacked.
+  field.Emit({{"WireType", wire_type},
+              {"packed",
+               [&] {
+                 if (!field.desc().is_packable()) return;
+                 field.Emit("$pb$::wire::WireType::Delimited");
+               }}},
+             R"rs(&[$pb$::wire::WireType::$WireType$, $packed$])rs");
+}
+
+void MessageWireTypes(Context<Descriptor> msg) {
+  for (int i = 0; i < msg.desc().field_count(); ++i) {
+    auto field = msg.WithDesc(msg.desc().field(i));
+    field.Emit({{"number", field.desc().number()},
+                {"wire_types", [&] { FieldWireTypes(field); }}},
+               R"rs(
+                 ($number$, $wire_types$),
+               )rs");
+  }
+}
+
+void MessageCreate(Context<Descriptor> msg) {
+  auto it = msg.opts().constructor_fields.find(msg.desc().full_name());
+  if (it == msg.opts().constructor_fields.end()) return;
+
+  std::vector<Context<FieldDescriptor>> fields;
+  for (const auto& name : it->second) {
+    // `RustGenerator::Generate()` has already rejected unknown fields.
+    const FieldDescriptor* field = msg.desc().FindFieldByName(name);
+    ABSL_CHECK(field != nullptr) << name;
+    fields.push_back(msg.WithDesc(field));
+  }
+
+  msg.Emit(
+      {
+          {"params",
+           [&] {
+             for (const auto& field : fields) {
+               field.Emit({{"param", RsSafeName(field.desc().name())},
+                           {"Scalar", PrimitiveRsTypeName(field)}},
+                          R"rs($param$: $Scalar$, )rs");
+             }
+           }},
+          {"setters",
+           [&] {
+             for (const auto& field : fields) {
+               std::string param = RsSafeName(field.desc().name());
+               field.Emit({{"field", field.desc().name()},
+                           {"val", field.desc().has_presence()
+                                       ? absl::StrCat("Some(", param, ")")
+                                       : param}},
+                          R"rs(
+                 __msg.$field$_set($val$);
+               )rs");
+             }
+           }},
+      },
+      R"rs(
+        /// Creates a message with the given fields set.
+        pub fn create($params$) -> Self {
+          let mut __msg = Self::new();
+          $setters$
+          __msg
+        }
+      )rs");
+}
+
+// `google.protobuf.Empty` carries no data, so let it be written as `().into()`
+// wherever a message is expected.
+void MessageFromUnit(Context<Descriptor> msg) {
+  if (msg.desc().full_name() != "google.protobuf.Empty") return;
+  msg.Emit({{"Msg", msg.desc().name()}}, R"rs(
+    impl $std$::convert::From<()> for $Msg$ {
+      fn from(_: ()) -> Self {
+        Self::new()
+      }
+    }
+  )rs");
+}
 }  // namespace
 
 MessageGenerator::MessageGenerator(Context<Descriptor> msg) {
@@ -222,11 +544,29 @@ void MessageGenerator::GenerateRs(Context<Descriptor> msg) {
   msg.Emit(
       {
           {"Msg", msg.desc().name()},
+          {"full_name", msg.desc().full_name()},
+          {"doc_hidden",
+           [&] {
+             if (IsDocHidden(msg)) {
+               msg.Emit("#[doc(hidden)]");
+             }
+           }},
           {"Msg.fields", [&] { MessageStructFields(msg); }},
           {"Msg::new", [&] { MessageNew(msg); }},
-          {"Msg::serialize", [&] { MessageSerialize(msg); }},
-          {"Msg::deserialize", [&] { MessageDeserialize(msg); }},
+          {"Msg::create", [&] { MessageCreate(msg); }},
+          {"Msg::FIELDS", [&] { MessageFieldTable(msg); }},
+          {"Msg::wire_types", [&] { MessageWireTypes(msg); }},
+          {"Msg::serialize", [&] { MessageSerialize(msg, false); }},
+          {"Msg::serialize_partial", [&] { MessageSerialize(msg, true); }},
+          {"Msg::serialize_deterministic",
+           [&] { MessageSerializeDeterministic(msg); }},
+          {"Msg::serialize_chunked", [&] { MessageSerializeChunked(msg); }},
+          {"Msg::serialize_append", [&] { MessageSerializeAppend(msg); }},
+          {"Msg::deserialize", [&] { MessageDeserialize(msg, false); }},
+          {"Msg::deserialize_partial", [&] { MessageDeserialize(msg, true); }},
+          {"Msg::space_used", [&] { MessageSpaceUsed(msg); }},
           {"Msg::drop", [&] { MessageDrop(msg); }},
+          {"impl_From_unit", [&] { MessageFromUnit(msg); }},
           {"Msg_externs", [&] { MessageExterns(msg); }},
           {"accessor_fns",
            [&] {
@@ -284,6 +624,7 @@ void MessageGenerator::GenerateRs(Context<Descriptor> msg) {
       },
       R"rs(
         #[allow(non_camel_case_types)]
+        $doc_hidden$
         pub struct $Msg$ {
           $Msg.fields$
         }
@@ -293,16 +634,216 @@ void MessageGenerator::GenerateRs(Context<Descriptor> msg) {
             $Msg::new$
           }
 
+          $Msg::create$
+
+          /// Metadata for the fields of this message that have generated
+          /// accessors, in declaration order. `string` fields with
+          /// `<field>_bytes()` accessors are listed with `FieldValue::Bytes`
+          /// values.
+          pub const FIELDS: &[$pb$::FieldInfo<Self>] = &[
+            $Msg::FIELDS$
+          ];
+
+          /// Returns the number and value of every set field in `FIELDS`.
+          pub fn fields(&self) -> impl $std$::iter::Iterator<Item = (u32, $pb$::FieldValue<'_>)> + '_ {
+            Self::FIELDS.iter().filter_map(move |f| Some((f.number, (f.get)(self)?)))
+          }
+
+          /// Checks that `data` is well-formed wire format for this message,
+          /// without building a message.
+          ///
+          /// On top of the structural checks of `wire::validate()`, every
+          /// field of this message present in `data` must have a wire type
+          /// matching its declared type. The contents of submessages and the
+          /// UTF-8 validity of strings are not checked; only `deserialize()`
+          /// does that.
+          pub fn validate_wire(data: &[u8]) -> Result<(), $pb$::ParseError> {
+            $pbi$::validate_wire(data, &[
+              $Msg::wire_types$
+            ])
+          }
+
+          /// Serializes the message.
+          ///
+          /// Panics if the message is too large for the kernel to serialize
+          /// (2GiB on the C++ kernel). Use `try_serialize()` to handle that
+          /// case.
           pub fn serialize(&self) -> $pb$::SerializedData {
+            $pbi$::expect_serialized(self.try_serialize())
+          }
+
+          /// Like `serialize()`, but returns an error if the message is too
+          /// large for the kernel to serialize.
+          pub fn try_serialize(&self) -> Result<$pb$::SerializedData, $pb$::SerializeError> {
+            $pbi$::record_serialize("$full_name$", || {
+              let data = self.serialize_uninstrumented();
+              let len = data.as_ref().map_or(0, |data| data.len());
+              (data, len)
+            })
+          }
+
+          fn serialize_uninstrumented(&self) -> Result<$pb$::SerializedData, $pb$::SerializeError> {
             $Msg::serialize$
           }
+
+          /// Serializes the message deterministically: serializing equal
+          /// messages with the same binary produces the same bytes.
+          ///
+          /// This is not a canonical encoding. The output may change between
+          /// protobuf releases and differ between languages, so it is only
+          /// suitable for keys that are computed and compared by the same
+          /// build.
+          pub fn serialize_deterministic(&self) -> $pb$::SerializedData {
+            $pbi$::record_serialize("$full_name$", || {
+              let data = $pbi$::expect_serialized({ $Msg::serialize_deterministic$ });
+              let len = data.len();
+              (data, len)
+            })
+          }
+
+          /// Feeds the deterministic serialization of the message to `hasher`
+          /// and returns it, for example
+          /// `msg.content_digest(Sha256::new())?.finalize()`.
+          ///
+          /// See `serialize_deterministic()` for the stability guarantees.
+          pub fn content_digest<H: $std$::io::Write>(&self, mut hasher: H) -> $std$::io::Result<H> {
+            hasher.write_all(&self.serialize_deterministic())?;
+            Ok(hasher)
+          }
+
+          /// Serializes the message, handing the output to `sink` in pieces of
+          /// at most `chunk_size` bytes.
+          ///
+          /// On the C++ kernel this never materializes the whole serialized
+          /// message in a single buffer.
+          ///
+          /// Panics if `chunk_size` is zero, or if the message is too large for
+          /// the kernel to serialize (see `serialize()`). If `sink` panics,
+          /// serialization stops and the panic is propagated to the caller.
+          pub fn serialize_chunked(&self, chunk_size: usize, mut sink: impl FnMut(&[u8])) {
+            $pbi$::record_serialize("$full_name$", || {
+              let mut len = 0;
+              {
+                let mut sink = |chunk: &[u8]| {
+                  len += chunk.len();
+                  sink(chunk)
+                };
+                $Msg::serialize_chunked$
+              }
+              ((), len)
+            })
+          }
+
+          /// Returns a serializer that writes the message into buffers of
+          /// bounded size, one `fill()` call at a time.
+          pub fn serializer(&self) -> $pb$::ResumableSerializer {
+            $pbi$::new_resumable_serializer(self.serialize())
+          }
+
+          /// Appends the serialized message to `out`.
+          ///
+          /// Panics if the message is too large for the kernel to serialize
+          /// (see `serialize()`).
+          pub fn serialize_append(&self, out: &mut $std$::vec::Vec<u8>) {
+            $pbi$::record_serialize("$full_name$", || {
+              let start = out.len();
+              {
+                $Msg::serialize_append$
+              }
+              ((), out.len() - start)
+            })
+          }
+
+          /// Replaces the contents of `out` with the serialized message,
+          /// reusing its capacity.
+          pub fn serialize_into(&self, out: &mut $std$::vec::Vec<u8>) {
+            out.clear();
+            self.serialize_append(out);
+          }
           pub fn deserialize(&mut self, data: &[u8]) -> Result<(), $pb$::ParseError> {
+            $pbi$::record_parse("$full_name$", data.len(), || {
+              self.deserialize_uninstrumented(data)
+            })
+          }
+
+          fn deserialize_uninstrumented(&mut self, data: &[u8]) -> Result<(), $pb$::ParseError> {
             $Msg::deserialize$
           }
 
+          /// Serializes the message and encodes it as base64.
+          pub fn serialize_base64(&self, alphabet: $pb$::base64::Alphabet) -> $std$::string::String {
+            $pb$::base64::encode(&self.serialize(), alphabet)
+          }
+
+          /// Decodes base64 `text` and deserializes the result.
+          pub fn deserialize_base64(
+            &mut self,
+            text: &str,
+            alphabet: $pb$::base64::Alphabet,
+          ) -> Result<(), $pb$::ParseError> {
+            let data = $pb$::base64::decode(text, alphabet)?;
+            self.deserialize(&data)
+          }
+
+          /// Like `deserialize()`, but only parses the top-level fields whose
+          /// numbers are in `field_numbers`. All other fields are skipped
+          /// without being decoded and are not retained as unknown fields.
+          ///
+          /// The metrics hook is passed the size of `data` before filtering.
+          pub fn deserialize_fields(
+            &mut self,
+            data: &[u8],
+            field_numbers: &[u32],
+          ) -> Result<(), $pb$::ParseError> {
+            $pbi$::record_parse("$full_name$", data.len(), || {
+              let data = $pb$::wire::retain_fields(data, field_numbers)?;
+              self.deserialize_uninstrumented(&data)
+            })
+          }
+
+          /// Captures the current state of the message, to be restored with
+          /// `rollback()`. Required fields need not be set.
+          ///
+          /// Panics if the message is too large for the kernel to serialize
+          /// (see `serialize()`).
+          pub fn checkpoint(&self) -> $pb$::Checkpoint<Self> {
+            $pbi$::new_checkpoint($pbi$::expect_serialized(self.serialize_partial()))
+          }
+
+          /// Restores the state captured by `checkpoint()`.
+          ///
+          /// Fails only if the message nests deeper than the kernel's
+          /// recursion limit, which a message built in code (rather than
+          /// parsed) can. The state of the message is then unspecified.
+          pub fn rollback(&mut self, checkpoint: &$pb$::Checkpoint<Self>) -> Result<(), $pb$::ParseError> {
+            self.deserialize_partial($pbi$::checkpoint_data(checkpoint))
+          }
+
+          fn serialize_partial(&self) -> Result<$pb$::SerializedData, $pb$::SerializeError> {
+            $Msg::serialize_partial$
+          }
+
+          fn deserialize_partial(&mut self, data: &[u8]) -> Result<(), $pb$::ParseError> {
+            $Msg::deserialize_partial$
+          }
+
+          /// Returns an estimate of the memory, in bytes, used by this message
+          /// including the memory of its fields and allocator overhead.
+          pub fn space_used(&self) -> usize {
+            $Msg::space_used$
+          }
+
           $accessor_fns$
         }  // impl $Msg$
 
+        impl $std$::default::Default for $Msg$ {
+          fn default() -> Self {
+            Self::new()
+          }
+        }
+
+        $impl_From_unit$
+
         //~ We implement drop unconditionally, so that `$Msg$: Drop` regardless
         //~ of kernel.
         impl $std$::ops::Drop for $Msg$ {
@@ -311,6 +852,17 @@ void MessageGenerator::GenerateRs(Context<Descriptor> msg) {
           }
         }
 
+        //~ SAFETY: `$Msg$` exclusively owns its message (and on upb, the arena
+        //~ backing it). Neither kernel ties that memory to the creating thread.
+        unsafe impl $std$::marker::Send for $Msg$ {}
+        unsafe impl $pb$::SendableMessage for $Msg$ {}
+
+        impl $pb$::DeterministicSerialize for $Msg$ {
+          fn serialize_deterministic(&self) -> $pb$::SerializedData {
+            $Msg$::serialize_deterministic(self)
+          }
+        }
+
         extern "C" {
           $Msg_externs$
 
@@ -348,6 +900,12 @@ void MessageGenerator::GenerateThunksCc(Context<Descriptor> msg) {
           {"delete_thunk", Thunk(msg, "delete")},
           {"serialize_thunk", Thunk(msg, "serialize")},
           {"deserialize_thunk", Thunk(msg, "deserialize")},
+          {"space_used_thunk", Thunk(msg, "space_used")},
+          {"serialize_chunked_thunk", Thunk(msg, "serialize_chunked")},
+          {"serialized_size_thunk", Thunk(msg, "serialized_size")},
+          {"serialize_to_array_thunk", Thunk(msg, "serialize_to_array")},
+          {"serialize_deterministic_thunk",
+           Thunk(msg, "serialize_deterministic")},
           {"nested_msg_thunks",
            [&] {
              for (int i = 0; i < msg.desc().nested_type_count(); ++i) {
@@ -375,12 +933,38 @@ void MessageGenerator::GenerateThunksCc(Context<Descriptor> msg) {
         extern $abi$ {
         void* $new_thunk$(){return new $QualifiedMsg$(); }
         void $delete_thunk$(void* ptr) { delete static_cast<$QualifiedMsg$*>(ptr); }
-        google::protobuf::rust_internal::SerializedData $serialize_thunk$($QualifiedMsg$* msg) {
-          return google::protobuf::rust_internal::SerializeMsg(msg);
+        bool $serialize_thunk$($QualifiedMsg$* msg,
+                               google::protobuf::rust_internal::SerializedData* out) {
+          return google::protobuf::rust_internal::SerializeMsg(msg, out);
         }
         bool $deserialize_thunk$($QualifiedMsg$* msg,
                                  google::protobuf::rust_internal::SerializedData data) {
-          return msg->ParseFromArray(data.data, data.len);
+          return google::protobuf::rust_internal::DeserializeMsg(msg, data);
+        }
+        bool $serialize_partial_thunk$($QualifiedMsg$* msg,
+                                       google::protobuf::rust_internal::SerializedData* out) {
+          return google::protobuf::rust_internal::SerializeMsgPartial(msg, out);
+        }
+        bool $deserialize_partial_thunk$($QualifiedMsg$* msg,
+                                         google::protobuf::rust_internal::SerializedData data) {
+          return google::protobuf::rust_internal::DeserializeMsgPartial(msg, data);
+        }
+        size_t $space_used_thunk$($QualifiedMsg$* msg) {
+          return msg->SpaceUsedLong();
+        }
+        bool $serialize_chunked_thunk$($QualifiedMsg$* msg, size_t chunk_size,
+                                       google::protobuf::rust_internal::ChunkSink sink) {
+          return google::protobuf::rust_internal::SerializeMsgChunked(msg, chunk_size, sink);
+        }
+        size_t $serialized_size_thunk$($QualifiedMsg$* msg) {
+          return google::protobuf::rust_internal::SerializedSize(msg);
+        }
+        void $serialize_to_array_thunk$($QualifiedMsg$* msg, char* data, size_t len) {
+          google::protobuf::rust_internal::SerializeMsgToArray(msg, data, len);
+        }
+        bool $serialize_deterministic_thunk$(
+            $QualifiedMsg$* msg, google::protobuf::rust_internal::SerializedData* out) {
+          return google::protobuf::rust_internal::SerializeMsgDeterministic(msg, out);
         }
 
         $accessor_thunks$
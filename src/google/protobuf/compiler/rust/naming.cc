@@ -32,6 +32,7 @@
 
 #include <string>
 
+#include "absl/container/flat_hash_set.h"
 #include "absl/log/absl_log.h"
 #include "absl/strings/str_cat.h"
 #include "absl/strings/str_replace.h"
@@ -106,6 +107,27 @@ std::string Thunk(Context<Descriptor> msg, absl::string_view op) {
   return absl::StrCat(prefix, GetUnderscoreDelimitedFullName(msg), "_", op);
 }
 
+std::string RsSafeName(absl::string_view name) {
+  static const auto* kNotRawIdentifiers =
+      new absl::flat_hash_set<absl::string_view>{"crate", "self", "Self",
+                                                 "super", "_"};
+  static const auto* kKeywords = new absl::flat_hash_set<absl::string_view>{
+      "abstract", "as", "async", "await", "become", "box", "break", "const",
+      "continue", "do", "dyn", "else", "enum", "extern", "false", "final", "fn",
+      "for", "if", "impl", "in", "let", "loop", "macro", "match", "mod", "move",
+      "mut", "override", "priv", "pub", "ref", "return", "static", "struct",
+      "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+      "virtual", "where", "while", "yield",
+  };
+  if (kNotRawIdentifiers->contains(name)) {
+    return absl::StrCat(name, "_");
+  }
+  if (kKeywords->contains(name)) {
+    return absl::StrCat("r#", name);
+  }
+  return std::string(name);
+}
+
 absl::string_view PrimitiveRsTypeName(Context<FieldDescriptor> field) {
   switch (field.desc().type()) {
     case FieldDescriptor::TYPE_BOOL:
@@ -141,6 +163,10 @@ std::string GetCrateRelativeQualifiedPath(Context<Descriptor> msg) {
   return absl::StrCat(RustModule(msg), "::", msg.desc().name());
 }
 
+bool IsDocHidden(Context<Descriptor> msg) {
+  return msg.opts().doc_hidden_messages.contains(msg.desc().full_name());
+}
+
 std::string FieldInfoComment(Context<FieldDescriptor> field) {
   absl::string_view label =
       field.desc().is_repeated() ? "repeated" : "optional";
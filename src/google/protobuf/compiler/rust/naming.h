@@ -55,11 +55,19 @@ bool IsSupportedFieldType(Context<FieldDescriptor> field);
 
 absl::string_view PrimitiveRsTypeName(Context<FieldDescriptor> field);
 
+// Returns `name` as a Rust identifier: Rust keywords are escaped as raw
+// identifiers (`r#type`), and the few keywords that can't be raw identifiers
+// (`self`, `super`, ...) get a trailing underscore.
+std::string RsSafeName(absl::string_view name);
+
 std::string FieldInfoComment(Context<FieldDescriptor> field);
 
 std::string RustModule(Context<Descriptor> msg);
 
 std::string GetCrateRelativeQualifiedPath(Context<Descriptor> msg);
+
+// Returns whether the generated items for `msg` should be `#[doc(hidden)]`.
+bool IsDocHidden(Context<Descriptor> msg);
 }  // namespace rust
 }  // namespace compiler
 }  // namespace protobuf